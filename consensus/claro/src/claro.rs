@@ -1,24 +1,37 @@
 // std
 use std::fmt::{Debug, Display, Formatter};
-use std::marker::PhantomData;
-use tracing::debug;
 // crates
 // internal
-use crate::query::NodeQuery;
+use crate::error::ClaroError;
+use crate::multi::{
+    ClaroObservation, ClaroState, MultiClaroSnapshot, MultiClaroSolver, MultiDecision,
+};
+use crate::query::{NodeQuery, NodeWeight, QueryResponse};
+use crate::round::{RoundComputation, RoundFormula};
+use crate::solver::ConsensusSolver;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Vote<Tx> {
     Yes(Tx),
     No(Tx),
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Opinion<Tx> {
     None(Tx),
     Yes(Tx),
     No(Tx),
 }
 
+impl<Tx> Vote<Tx> {
+    /// Binary vote as a `bool` conflict set value, `true` for yes
+    pub fn preference(&self) -> bool {
+        matches!(self, Vote::Yes(_))
+    }
+}
+
 impl<Tx> Opinion<Tx> {
     pub fn flip(self) -> Self {
         match self {
@@ -27,14 +40,53 @@ impl<Tx> Opinion<Tx> {
             none => none,
         }
     }
+
+    /// Build an opinion over `tx` from a `bool` conflict set preference
+    pub fn from_preference(tx: Tx, preference: Option<bool>) -> Self {
+        match preference {
+            Some(true) => Opinion::Yes(tx),
+            Some(false) => Opinion::No(tx),
+            None => Opinion::None(tx),
+        }
+    }
+
+    /// Binary opinion as a `bool` conflict set preference, `true` for yes
+    pub fn preference(&self) -> Option<bool> {
+        match self {
+            Opinion::Yes(_) => Some(true),
+            Opinion::No(_) => Some(false),
+            Opinion::None(_) => None,
+        }
+    }
+
+    pub fn tx(&self) -> &Tx {
+        match self {
+            Opinion::Yes(tx) | Opinion::No(tx) | Opinion::None(tx) => tx,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Decision<Tx> {
     Decided(Opinion<Tx>),
     Undecided(Opinion<Tx>),
 }
 
+impl<Tx> Decision<Tx> {
+    /// Build a decision over `tx` from a `bool` conflict set decision
+    pub fn from_multi(tx: Tx, decision: MultiDecision<bool>) -> Self {
+        match decision {
+            MultiDecision::Decided(preference) => {
+                Decision::Decided(Opinion::from_preference(tx, preference))
+            }
+            MultiDecision::Undecided(preference) => {
+                Decision::Undecided(Opinion::from_preference(tx, preference))
+            }
+        }
+    }
+}
+
 impl<Tx> Display for Opinion<Tx> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let tag = match self {
@@ -83,55 +135,23 @@ impl<Tx> From<Decision<Tx>> for Option<Vote<Tx>> {
     }
 }
 
-#[allow(dead_code)]
-/// Claro round computed evidence, confidence and alpha
-pub struct ClaroRoundCalculation {
-    confidence: f32,
-    e1: f32,
-    e2: f32,
-    e: f32,
-    alpha: f32,
-}
-
-/// Claro internal state
-#[derive(Default, Debug)]
-pub struct ClaroState {
-    /// Positive votes seen
-    evidence: usize,
-    /// Total votes seen, positive and negative
-    evidence_accumulated: usize,
-    /// Votes ratio
-    confidence: usize,
-}
-
-impl ClaroState {
-    pub fn update_confidence<Tx>(&mut self, votes: &[Vote<Tx>]) {
-        let total_votes = votes.len();
-        self.confidence = self.confidence.saturating_add(total_votes);
-    }
-
-    pub fn update_evidence<Tx>(&mut self, votes: &[Vote<Tx>]) {
-        let total_votes = votes.len();
-        let total_yes = votes.iter().filter(|v| matches!(v, Vote::Yes(_))).count();
-        self.evidence = self.evidence.saturating_add(total_yes);
-        self.evidence_accumulated = self.evidence_accumulated.saturating_add(total_votes);
-    }
-
-    pub fn confidence(&self) -> usize {
-        self.confidence
-    }
-
-    pub fn evidence(&self) -> usize {
-        self.evidence
-    }
-
-    pub fn evidence_accumulated(&self) -> usize {
-        self.evidence_accumulated
-    }
+/// Query size behaviour for rounds that reach a quorum after the query has grown
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum QueryGrowthPolicy {
+    /// Keep the grown query size
+    #[default]
+    Keep,
+    /// Go back to [`QueryConfiguration::initial_query_size`]
+    Reset,
+    /// Undo a single growth step, never going below [`QueryConfiguration::initial_query_size`]
+    Shrink,
 }
 
 /// Node query configuration
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct QueryConfiguration {
     /// How many nodes to query
     pub query_size: usize,
@@ -141,6 +161,9 @@ pub struct QueryConfiguration {
     pub query_multiplier: usize,
     /// Max value for [`QueryConfiguration::query_multiplier`]
     pub max_multiplier: usize,
+    /// What to do with a grown query once a quorum is found again
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub growth_policy: QueryGrowthPolicy,
 }
 
 impl QueryConfiguration {
@@ -152,151 +175,333 @@ impl QueryConfiguration {
             // TODO: Should this be configurable? Runtime vs Compiled
             query_multiplier: 2,
             max_multiplier: 4,
+            growth_policy: QueryGrowthPolicy::Keep,
+        }
+    }
+
+    /// Max size the query can grow up to
+    fn max_query_size(&self) -> Result<usize, ClaroError> {
+        self.initial_query_size
+            .checked_mul(self.max_multiplier)
+            .ok_or_else(|| {
+                ClaroError::InvalidConfiguration(format!(
+                    "initial_query_size {} * max_multiplier {} overflows",
+                    self.initial_query_size, self.max_multiplier
+                ))
+            })
+    }
+
+    /// Check that the query can grow without overflowing
+    pub fn validate(&self) -> Result<(), ClaroError> {
+        if self.query_multiplier == 0 || self.max_multiplier == 0 {
+            return Err(ClaroError::InvalidConfiguration(
+                "query_multiplier and max_multiplier must be at least 1".to_string(),
+            ));
         }
+        self.max_query_size().map(|_| ())
     }
 
     /// Increment query based upon configuration
     /// query_size = min(query_size * growth_constant, initial_query_size * growth_max)
-    fn grow(&mut self) {
-        self.query_size = (self.query_size * self.query_multiplier)
-            .min(self.initial_query_size * self.max_multiplier);
+    pub(crate) fn grow(&mut self) -> Result<(), ClaroError> {
+        self.query_size = self
+            .query_size
+            .saturating_mul(self.query_multiplier)
+            .min(self.max_query_size()?);
+        Ok(())
+    }
+
+    /// Decrement query based upon [`QueryConfiguration::growth_policy`]
+    pub(crate) fn relax(&mut self) {
+        self.query_size = match self.growth_policy {
+            QueryGrowthPolicy::Keep => self.query_size,
+            QueryGrowthPolicy::Reset => self.initial_query_size,
+            QueryGrowthPolicy::Shrink => self
+                .query_size
+                .checked_div(self.query_multiplier)
+                .unwrap_or(self.initial_query_size)
+                .max(self.initial_query_size),
+        };
+    }
+}
+
+/// How votes add up into evidence and confidence
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum EvidenceWeighting {
+    /// Every vote counts as `1.0`
+    #[default]
+    Count,
+    /// Every vote counts as the responder stake, [`crate::ResponseMetadata::weight`]
+    Stake,
+}
+
+impl EvidenceWeighting {
+    /// Weight of the vote held by `response`
+    pub fn weight<Tx>(&self, response: &QueryResponse<Tx>) -> Result<NodeWeight, ClaroError> {
+        match self {
+            EvidenceWeighting::Count => Ok(1.0),
+            EvidenceWeighting::Stake => response
+                .metadata
+                .weight
+                .ok_or_else(|| ClaroError::MissingWeight(response.node_id.clone())),
+        }
+    }
+}
+
+/// How a node without an opinion adopts one
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum BootstrapPolicy {
+    /// Adopt the first vote seen
+    #[default]
+    FirstVote,
+    /// Adopt the value with the most votes weight in the first sample with any vote, ties go to
+    /// the value seen first
+    Majority,
+    /// Wait for a sample where a value reaches the solver quorum
+    Quorum,
+}
+
+impl BootstrapPolicy {
+    /// Value to adopt out of `votes`, if any, `quorum` being the votes weight a value must reach
+    /// under [`BootstrapPolicy::Quorum`]
+    pub fn adopt<V: Clone + PartialEq>(
+        &self,
+        votes: &[(V, NodeWeight)],
+        quorum: NodeWeight,
+    ) -> Option<V> {
+        if let BootstrapPolicy::FirstVote = self {
+            return votes.first().map(|(vote, _)| vote.clone());
+        }
+        let mut tally: Vec<(&V, NodeWeight)> = Vec::new();
+        for (vote, weight) in votes {
+            match tally.iter_mut().find(|(value, _)| *value == vote) {
+                Some((_, total)) => *total += weight,
+                None => tally.push((vote, *weight)),
+            }
+        }
+        let (value, weight) =
+            tally
+                .into_iter()
+                .reduce(|best, entry| if entry.1 > best.1 { entry } else { best })?;
+        match self {
+            BootstrapPolicy::Quorum if weight < quorum => None,
+            _ => Some(value.clone()),
+        }
     }
 }
 
 /// Claro algorithm configuration
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ClaroConfiguration {
     pub evidence_alpha: f32,
     pub evidence_alpha_2: f32,
     pub confidence_beta: f32,
+    /// Confidence weight needed to trust accumulated evidence, in the [`EvidenceWeighting`] unit
     pub look_ahead: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub weighting: EvidenceWeighting,
+    /// How rounds blend evidence, confidence and alpha, unless the solver is built with its own
+    /// [`RoundComputation`]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub formula: RoundFormula,
+    /// How nodes without an opinion adopt one, [`BootstrapPolicy::Quorum`] waiting for a value to
+    /// get `evidence_alpha` of a sample votes weight
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bootstrap: BootstrapPolicy,
     pub query: QueryConfiguration,
 }
 
+impl ClaroConfiguration {
+    /// Check that thresholds are normalized and the query configuration is sound
+    pub fn validate(&self) -> Result<(), ClaroError> {
+        for (name, value) in [
+            ("evidence_alpha", self.evidence_alpha),
+            ("evidence_alpha_2", self.evidence_alpha_2),
+            ("confidence_beta", self.confidence_beta),
+        ] {
+            if !(0f32..=1f32).contains(&value) {
+                return Err(ClaroError::InvalidConfiguration(format!(
+                    "{name} must be within [0.0, 1.0], got {value}"
+                )));
+            }
+        }
+        self.formula.validate()?;
+        self.query.validate()
+    }
+}
+
+/// [`ClaroSolver`] in-flight state, see [`ClaroSolver::snapshot`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClaroSnapshot<Tx> {
+    pub tx: Tx,
+    pub solver: MultiClaroSnapshot<bool>,
+}
+
 /// Claro computation object
-pub struct ClaroSolver<Tx> {
-    _phantom: PhantomData<Tx>,
-    /// Internal state
-    state: ClaroState,
-    /// Configuration, including node query configuration
-    configuration: ClaroConfiguration,
-    /// Current tx decision
-    decision: Decision<Tx>,
-    /// Node query setup for current node
-    node_query: NodeQuery,
+/// Binary specialization of [`MultiClaroSolver`] over a `{yes, no}` conflict set for a single tx.
+pub struct ClaroSolver<Tx, R = RoundFormula> {
+    /// Tx being decided
+    tx: Tx,
+    /// Conflict set solver, `true` stands for yes
+    solver: MultiClaroSolver<bool, R>,
 }
 
 // TODO: can we remove clone here?
 impl<Tx: Clone + Debug> ClaroSolver<Tx> {
-    pub fn new(tx: Tx, configuration: ClaroConfiguration, node_query: NodeQuery) -> Self {
-        Self {
-            _phantom: Default::default(),
-            state: Default::default(),
-            decision: Decision::Undecided(Opinion::Yes(tx)),
-            configuration,
-            node_query,
-        }
+    /// Build a solver for `tx`, failing on an invalid `configuration`
+    pub fn new(
+        tx: Tx,
+        configuration: ClaroConfiguration,
+        node_query: NodeQuery,
+    ) -> Result<Self, ClaroError> {
+        Self::with_initial_opinion(configuration, node_query, Opinion::Yes(tx))
     }
 
+    /// Build a solver starting from `opinion`, failing on an invalid `configuration`
     pub fn with_initial_opinion(
         configuration: ClaroConfiguration,
         node_query: NodeQuery,
         opinion: Opinion<Tx>,
-    ) -> Self {
-        Self {
-            _phantom: Default::default(),
-            state: Default::default(),
-            decision: Decision::Undecided(opinion),
-            configuration,
-            node_query,
-        }
+    ) -> Result<Self, ClaroError> {
+        Self::with_round_computation(configuration, node_query, opinion, configuration.formula)
     }
 
-    /// Compute a single round state from already queried nodes votes
-    fn round_state(&self, votes: &[Vote<Tx>]) -> ClaroRoundCalculation {
-        let total_votes = votes.len();
-        let yes_votes = votes.iter().filter(|&v| matches!(v, Vote::Yes(_))).count();
-        let confidence = self.state.confidence() as f32
-            / (self.state.confidence() as f32 + self.configuration.look_ahead as f32);
-
-        let e1 = yes_votes as f32 / total_votes as f32;
-        let e2 = self.state.evidence() as f32 / self.state.evidence_accumulated() as f32;
-        let e = e1 * (1f32 - confidence) + e2 * confidence;
-        let alpha = self.configuration.evidence_alpha * (1f32 - confidence)
-            + self.configuration.evidence_alpha_2 * confidence;
-
-        ClaroRoundCalculation {
-            confidence,
-            e1,
-            e2,
-            e,
-            alpha,
-        }
+    /// Resume a solver from a previously taken snapshot
+    /// Fails if the snapshot configuration does not [`ClaroConfiguration::validate`]
+    pub fn restore(snapshot: ClaroSnapshot<Tx>) -> Result<Self, ClaroError> {
+        let formula = snapshot.solver.configuration.formula;
+        Self::restore_with_round_computation(snapshot, formula)
     }
+}
 
-    /// Compute a single round
-    /// mutates the decision parameter upon this round data
-    pub fn step(&mut self, tx: Tx, votes: &[Vote<Tx>]) {
-        assert!(matches!(self.decision, Decision::Undecided(_)));
-        debug!(votes = ?votes);
-        if let Decision::Undecided(Opinion::None(_)) = self.decision() {
-            if let Some(vote) = votes.first().cloned() {
-                self.decision = Decision::Undecided(vote.into());
-            }
-        }
+impl<Tx: Clone + Debug, R: RoundComputation> ClaroSolver<Tx, R> {
+    /// Same as [`ClaroSolver::with_initial_opinion`], computing rounds with `round_computation`
+    /// instead of the [`ClaroConfiguration::formula`] preset
+    pub fn with_round_computation(
+        configuration: ClaroConfiguration,
+        node_query: NodeQuery,
+        opinion: Opinion<Tx>,
+        round_computation: R,
+    ) -> Result<Self, ClaroError> {
+        let preference = opinion.preference();
+        Ok(Self {
+            tx: opinion.tx().clone(),
+            solver: MultiClaroSolver::with_round_computation(
+                [true, false],
+                configuration,
+                node_query,
+                preference,
+                round_computation,
+            )?,
+        })
+    }
 
-        if !votes.is_empty() {
-            self.state.update_evidence(votes);
-            self.state.update_confidence(votes);
-
-            let ClaroRoundCalculation {
-                e,
-                alpha,
-                confidence,
-                ..
-            } = self.round_state(votes);
-            debug!(e = e, alpha = alpha);
-            if e > alpha {
-                self.decision = Decision::Undecided(Opinion::Yes(tx));
-            } else if e < 1f32 - alpha {
-                self.decision = Decision::Undecided(Opinion::No(tx));
-            } else {
-                self.configuration.query.grow();
-            }
-            if confidence > self.configuration.confidence_beta {
-                self.decision = Decision::Decided(self.opinion());
-            }
+    /// Same as [`ClaroSolver::restore`], computing rounds with `round_computation`
+    pub fn restore_with_round_computation(
+        snapshot: ClaroSnapshot<Tx>,
+        round_computation: R,
+    ) -> Result<Self, ClaroError> {
+        Ok(Self {
+            tx: snapshot.tx,
+            solver: MultiClaroSolver::restore_with_round_computation(
+                snapshot.solver,
+                round_computation,
+            )?,
+        })
+    }
+
+    /// Copy of the in-flight state, enough to [`ClaroSolver::restore`] it later on
+    pub fn snapshot(&self) -> ClaroSnapshot<Tx> {
+        ClaroSnapshot {
+            tx: self.tx.clone(),
+            solver: self.solver.snapshot(),
         }
     }
 
+    /// Compute a single round
+    /// mutates the decision parameter upon this round data, missing responses are ignored
+    pub fn step(
+        &mut self,
+        tx: Tx,
+        responses: &[QueryResponse<Tx>],
+    ) -> Result<ClaroObservation<bool>, ClaroError> {
+        let votes = weighted_votes(&self.solver.configuration().weighting, responses)?;
+        let observation = self.solver.step_weighted(&votes)?;
+        self.tx = tx;
+        Ok(observation)
+    }
+
     /// Derive vote from it's current decision
     pub fn vote(&self) -> Option<Vote<Tx>> {
-        self.decision.clone().into()
+        self.decision().into()
     }
 
     pub fn decision(&self) -> Decision<Tx> {
-        self.decision.clone()
+        Decision::from_multi(self.tx.clone(), self.solver.decision())
     }
 
     pub fn opinion(&self) -> Opinion<Tx> {
-        match &self.decision {
-            Decision::Decided(o) | Decision::Undecided(o) => o.clone(),
-        }
+        Opinion::from_preference(self.tx.clone(), self.solver.preference())
     }
 
     pub fn state(&self) -> &ClaroState {
-        &self.state
+        self.solver.state()
     }
 
     pub fn node_query(&self) -> &NodeQuery {
-        &self.node_query
+        self.solver.node_query()
+    }
+
+    /// Current effective query size, including any growth applied so far
+    pub fn query_size(&self) -> usize {
+        self.solver.query_size()
+    }
+}
+
+/// Binary preference of every vote in `responses`, weighted as stated by `weighting`
+pub(crate) fn weighted_votes<Tx>(
+    weighting: &EvidenceWeighting,
+    responses: &[QueryResponse<Tx>],
+) -> Result<Vec<(bool, NodeWeight)>, ClaroError> {
+    responses
+        .iter()
+        .filter_map(|response| Some((response.as_vote()?.preference(), response)))
+        .map(|(preference, response)| Ok((preference, weighting.weight(response)?)))
+        .collect()
+}
+
+impl<Tx: Clone + Debug, R: RoundComputation> ConsensusSolver<Tx> for ClaroSolver<Tx, R> {
+    type Error = ClaroError;
+
+    fn step(&mut self, tx: Tx, responses: &[QueryResponse<Tx>]) -> Result<(), Self::Error> {
+        ClaroSolver::step(self, tx, responses).map(|_| ())
+    }
+
+    fn decision(&self) -> Decision<Tx> {
+        ClaroSolver::decision(self)
+    }
+
+    fn node_query(&self) -> &NodeQuery {
+        ClaroSolver::node_query(self)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::claro::{ClaroConfiguration, ClaroSolver, Decision, QueryConfiguration, Vote};
-    use crate::query::NodeQuery;
+    use crate::claro::{
+        BootstrapPolicy, ClaroConfiguration, ClaroSolver, Decision, QueryConfiguration,
+        QueryGrowthPolicy, Vote,
+    };
+    use crate::error::ClaroError;
+    use crate::multi::{MultiClaroSolver, MultiDecision};
+    use crate::query::{NodeQuery, QueryResponse, ResponseVote};
+    use crate::testing::configuration;
     use crate::testing::query::*;
     use crate::{Opinion, VoteQuery};
     use std::fmt::Debug;
@@ -314,17 +519,17 @@ mod test {
             evidence_alpha_2: 0.01,
             confidence_beta: 0.01,
             look_ahead: 1,
-            query: QueryConfiguration::new(10),
+            ..configuration()
         };
         let node_query = NodeQuery::new(config.query.query_size, "node_1".into());
-        let mut solver = ClaroSolver::new(tx.clone(), config, node_query);
+        let mut solver = ClaroSolver::new(tx.clone(), config, node_query).unwrap();
 
         assert_eq!(
-            solver.decision,
+            solver.decision(),
             Decision::Undecided(Opinion::Yes(tx.clone()))
         );
-        solver.step(tx, votes);
-        assert_eq!(solver.decision, expected);
+        solver.step(tx, &responses(votes)).unwrap();
+        assert_eq!(solver.decision(), expected);
     }
 
     #[test]
@@ -348,14 +553,210 @@ mod test {
             evidence_alpha_2: 0.01,
             confidence_beta: 0.01,
             look_ahead: 1,
-            query: QueryConfiguration::new(10),
+            ..configuration()
         };
 
         let node_query = NodeQuery::new(config.query.query_size, "node_1".into());
-        let mut solver = ClaroSolver::new(EmptyTx, config, node_query);
+        let mut solver = ClaroSolver::new(EmptyTx, config, node_query).unwrap();
 
-        let query = fixed_query.query(&solver.node_query, EmptyTx).await;
-        solver.step(EmptyTx, &query);
+        let query = fixed_query.query(solver.node_query(), EmptyTx).await;
+        solver.step(EmptyTx, &query).unwrap();
         assert_eq!(solver.vote(), Some(vote))
     }
+
+    #[test]
+    fn query_growth_and_reset() {
+        let config = ClaroConfiguration {
+            evidence_alpha_2: 0.8,
+            confidence_beta: 0.99,
+            look_ahead: 100,
+            query: QueryConfiguration {
+                growth_policy: QueryGrowthPolicy::Reset,
+                ..QueryConfiguration::new(10)
+            },
+            ..configuration()
+        };
+        let node_query = NodeQuery::new(1000, "node_1".into());
+        let mut solver = ClaroSolver::new(true, config, node_query).unwrap();
+        assert_eq!(solver.node_query().query_size(), 10);
+
+        let split: Vec<_> = (0..10)
+            .map(|i| {
+                if i % 2 == 0 {
+                    Vote::Yes(true)
+                } else {
+                    Vote::No(true)
+                }
+            })
+            .collect();
+        solver.step(true, &responses(&split)).unwrap();
+        assert_eq!(solver.query_size(), 20);
+        assert_eq!(solver.node_query().query_size(), 20);
+
+        solver
+            .step(true, &responses(&[Vote::Yes(true); 20]))
+            .unwrap();
+        assert_eq!(solver.query_size(), 10);
+        assert_eq!(solver.node_query().query_size(), 10);
+    }
+
+    #[test]
+    fn missing_responses_are_ignored() {
+        let config = configuration();
+        let node_query = NodeQuery::new(10, "node_1".into());
+        let mut solver = ClaroSolver::new(true, config, node_query).unwrap();
+        let mut round = responses(&[Vote::No(true); 3]);
+        round.push(QueryResponse::new("3".into(), ResponseVote::Missing));
+        round.push(QueryResponse::new("4".into(), ResponseVote::TimedOut));
+        solver.step(true, &round).unwrap();
+        assert_eq!(solver.state().confidence(), 3.0);
+        assert_eq!(solver.opinion(), Opinion::No(true));
+    }
+
+    #[test]
+    fn step_observation() {
+        let config = configuration();
+        let node_query = NodeQuery::new(10, "node_1".into());
+        let mut solver = ClaroSolver::new(true, config, node_query).unwrap();
+        let split = [Vote::Yes(true), Vote::No(true)];
+        let observation = solver.step(true, &responses(&split)).unwrap();
+        assert_eq!(observation.tally, [(true, 1.0), (false, 1.0)]);
+        assert_eq!(observation.calculations.len(), 2);
+        let (value, calculation) = observation.calculations[0];
+        assert!(value);
+        assert_eq!(calculation.e1, 0.5);
+        assert_eq!(calculation.e2, 0.5);
+        assert!(calculation.e < calculation.alpha);
+        assert!(observation.query_grew);
+        assert_eq!(observation.query_size, 20);
+        assert_eq!(observation.decision, MultiDecision::Undecided(Some(true)));
+
+        let observation = solver.step(true, &[]).unwrap();
+        assert!(observation.calculations.is_empty());
+        assert!(!observation.query_grew);
+    }
+
+    #[test]
+    fn bootstrap_policies() {
+        let votes = [("red", 1.0), ("blue", 1.0), ("blue", 1.0), ("green", 1.0)];
+        assert_eq!(BootstrapPolicy::FirstVote.adopt(&votes, 3.0), Some("red"));
+        assert_eq!(BootstrapPolicy::Majority.adopt(&votes, 3.0), Some("blue"));
+        assert_eq!(BootstrapPolicy::Quorum.adopt(&votes, 3.0), None);
+        assert_eq!(BootstrapPolicy::Quorum.adopt(&votes, 2.0), Some("blue"));
+        assert_eq!(BootstrapPolicy::Majority.adopt::<&str>(&[], 0.0), None);
+
+        let config = ClaroConfiguration {
+            bootstrap: BootstrapPolicy::Quorum,
+            ..configuration()
+        };
+        let node_query = NodeQuery::new(10, "node_1".into());
+        let mut solver =
+            ClaroSolver::with_initial_opinion(config, node_query, Opinion::None(true)).unwrap();
+        solver
+            .step(true, &responses(&[Vote::Yes(true), Vote::No(true)]))
+            .unwrap();
+        assert_eq!(solver.opinion(), Opinion::None(true));
+        solver
+            .step(true, &responses(&[Vote::No(true); 10]))
+            .unwrap();
+        assert_eq!(solver.opinion(), Opinion::No(true));
+    }
+
+    #[test]
+    fn step_decided_fails() {
+        let votes: Vec<_> = (0..10).map(|_| Vote::<bool>::Yes(true)).collect();
+        let config = ClaroConfiguration {
+            evidence_alpha: 0.01,
+            evidence_alpha_2: 0.01,
+            confidence_beta: 0.01,
+            look_ahead: 1,
+            ..configuration()
+        };
+        let node_query = NodeQuery::new(config.query.query_size, "node_1".into());
+        let mut solver = ClaroSolver::new(true, config, node_query).unwrap();
+        solver.step(true, &responses(&votes)).unwrap();
+        assert_eq!(
+            solver.step(true, &responses(&votes)),
+            Err(ClaroError::AlreadyDecided)
+        );
+    }
+
+    #[test]
+    fn snapshot_and_restore() {
+        let config = configuration();
+        let node_query = NodeQuery::new(10, "node_1".into());
+        let mut solver = ClaroSolver::new(true, config, node_query).unwrap();
+        let split = [Vote::Yes(true), Vote::No(true)];
+        solver.step(true, &responses(&split)).unwrap();
+        solver
+            .step(true, &responses(&[Vote::No(true); 10]))
+            .unwrap();
+
+        let mut restored = ClaroSolver::restore(solver.snapshot()).unwrap();
+        assert_eq!(restored.decision(), solver.decision());
+        assert_eq!(restored.query_size(), 20);
+        assert_eq!(restored.state().evidence(), solver.state().evidence());
+        while let Decision::Undecided(_) = solver.decision() {
+            solver
+                .step(true, &responses(&[Vote::No(true); 20]))
+                .unwrap();
+            restored
+                .step(true, &responses(&[Vote::No(true); 20]))
+                .unwrap();
+            assert_eq!(restored.decision(), solver.decision());
+        }
+
+        let mut invalid = solver.snapshot();
+        invalid.solver.configuration.query.query_multiplier = 0;
+        assert!(matches!(
+            ClaroSolver::restore(invalid),
+            Err(ClaroError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn snapshot_serde_round_trip() {
+        let config = configuration();
+        let node_query = NodeQuery::new(10, "node_1".into());
+        let mut solver = ClaroSolver::new(true, config, node_query).unwrap();
+        solver
+            .step(true, &responses(&[Vote::Yes(true); 10]))
+            .unwrap();
+
+        let serialized = serde_json::to_string(&solver.snapshot()).unwrap();
+        let restored =
+            ClaroSolver::<bool>::restore(serde_json::from_str(&serialized).unwrap()).unwrap();
+        assert_eq!(restored.decision(), solver.decision());
+        assert_eq!(restored.state().confidence(), 10.0);
+        assert_eq!(restored.node_query().node_id(), "node_1");
+    }
+
+    #[test]
+    fn invalid_configuration_is_rejected() {
+        let overflowing = ClaroConfiguration {
+            query: QueryConfiguration {
+                max_multiplier: usize::MAX,
+                ..QueryConfiguration::new(10)
+            },
+            ..configuration()
+        };
+        assert!(matches!(
+            overflowing.validate(),
+            Err(ClaroError::InvalidConfiguration(_))
+        ));
+        let node_query = NodeQuery::new(10, "node_1".into());
+        assert!(matches!(
+            ClaroSolver::new(true, overflowing, node_query.clone()),
+            Err(ClaroError::InvalidConfiguration(_))
+        ));
+        let unnormalized = ClaroConfiguration {
+            evidence_alpha: 1.5,
+            ..configuration()
+        };
+        assert!(matches!(
+            MultiClaroSolver::new([true, false], unnormalized, node_query, Some(true)),
+            Err(ClaroError::InvalidConfiguration(_))
+        ));
+    }
 }
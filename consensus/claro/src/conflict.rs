@@ -0,0 +1,289 @@
+// std
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+// crates
+use tracing::debug;
+// internal
+use crate::claro::{ClaroConfiguration, Vote};
+use crate::error::ClaroError;
+use crate::multi::{MultiClaroSolver, MultiDecision};
+use crate::query::{NodeQuery, NodeWeight, QueryResponse, VoteQuery};
+use crate::tracing::CLARO_TARGET_TAG;
+
+/// Status of a single transaction tracked by [`ConflictSets`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TxStatus {
+    /// Undecided, currently preferred within its conflict set
+    Preferred,
+    /// Undecided, another transaction of its conflict set is preferred
+    NotPreferred,
+    /// Accepted, no other transaction of its conflict set can be
+    Accepted,
+    /// Rejected, either outvoted or arrived after its conflict set was decided
+    Rejected,
+}
+
+/// Mutually exclusive transactions, decided by a single solver
+struct ConflictSet<Tx> {
+    txs: Vec<Tx>,
+    /// `None` stands for responders preferring none of the set transactions
+    solver: MultiClaroSolver<Option<Tx>>,
+}
+
+impl<Tx: Clone + PartialEq + Debug> ConflictSet<Tx> {
+    /// Solver decision, preferring none of the set transactions reading as no preference
+    fn decision(&self) -> MultiDecision<Tx> {
+        match self.solver.decision() {
+            MultiDecision::Decided(tx) => MultiDecision::Decided(tx.flatten()),
+            MultiDecision::Undecided(tx) => MultiDecision::Undecided(tx.flatten()),
+        }
+    }
+}
+
+/// Avalanche style conflict sets manager
+/// Tracks many transactions at once, grouped by a conflict set key. Every conflict set is decided
+/// by its own [`MultiClaroSolver`], so at most one transaction per set is ever accepted.
+/// Pending transactions are all queried together in a single [`VoteQuery`] round, the query
+/// batch being the list of pending transactions. Each responder answers with
+/// [`Vote::Yes`] holding the subset of the batch it prefers, [`Vote::No`] counts as no preference
+/// at all. Every answering responder weights on every pending set, those preferring none of its
+/// transactions as support for none of them, so a transaction only known to a minority of the
+/// sample is not accepted.
+pub struct ConflictSets<Tx, K> {
+    configuration: ClaroConfiguration,
+    node_query: NodeQuery,
+    /// Conflict sets, in insertion order
+    sets: Vec<ConflictSet<Tx>>,
+    /// Conflict set key to position in [`ConflictSets::sets`]
+    keys: HashMap<K, usize>,
+    /// Transaction to position in [`ConflictSets::sets`]
+    txs: HashMap<Tx, usize>,
+}
+
+impl<Tx, K> ConflictSets<Tx, K>
+where
+    Tx: Clone + Eq + Hash + Debug,
+    K: Eq + Hash,
+{
+    /// Fails if `configuration` does not [`ClaroConfiguration::validate`]
+    pub fn new(
+        configuration: ClaroConfiguration,
+        node_query: NodeQuery,
+    ) -> Result<Self, ClaroError> {
+        configuration.validate()?;
+        Ok(Self {
+            configuration,
+            node_query,
+            sets: Vec::new(),
+            keys: HashMap::new(),
+            txs: HashMap::new(),
+        })
+    }
+
+    /// Track `tx` as part of the `conflict_set` conflict set
+    /// The first transaction of a set is its initial preference. Transactions joining an
+    /// already decided set are rejected right away, already tracked ones keep their set.
+    pub fn insert(&mut self, tx: Tx, conflict_set: K) -> Result<TxStatus, ClaroError> {
+        if let Some(status) = self.status(&tx) {
+            return Ok(status);
+        }
+        let position = match self.keys.get(&conflict_set) {
+            Some(&position) => {
+                let set = &mut self.sets[position];
+                set.solver.insert_value(&Some(tx.clone()));
+                set.txs.push(tx.clone());
+                position
+            }
+            None => {
+                let solver = MultiClaroSolver::new(
+                    [Some(tx.clone())],
+                    self.configuration,
+                    self.node_query.clone(),
+                    Some(Some(tx.clone())),
+                )?;
+                self.sets.push(ConflictSet {
+                    txs: vec![tx.clone()],
+                    solver,
+                });
+                self.keys.insert(conflict_set, self.sets.len() - 1);
+                self.sets.len() - 1
+            }
+        };
+        self.txs.insert(tx.clone(), position);
+        Ok(self
+            .status(&tx)
+            .expect("Transaction was just inserted in a conflict set"))
+    }
+
+    /// Status of `tx`, `None` if it is not tracked
+    pub fn status(&self, tx: &Tx) -> Option<TxStatus> {
+        let set = &self.sets[*self.txs.get(tx)?];
+        let decision = set.decision();
+        let preferred = decision.preference() == Some(tx);
+        Some(match (decision.is_decided(), preferred) {
+            (false, true) => TxStatus::Preferred,
+            (false, false) => TxStatus::NotPreferred,
+            (true, true) => TxStatus::Accepted,
+            (true, false) => TxStatus::Rejected,
+        })
+    }
+
+    /// Decision of the conflict set `conflict_set`, `None` if it is not tracked
+    pub fn decision(&self, conflict_set: &K) -> Option<MultiDecision<Tx>> {
+        self.keys
+            .get(conflict_set)
+            .map(|&position| self.sets[position].decision())
+    }
+
+    /// Transactions of every undecided conflict set, in insertion order
+    pub fn pending(&self) -> Vec<Tx> {
+        self.pending_sets()
+            .flat_map(|set| set.txs.iter().cloned())
+            .collect()
+    }
+
+    /// Accepted transactions, at most one per conflict set
+    pub fn accepted(&self) -> Vec<Tx> {
+        self.sets
+            .iter()
+            .filter_map(|set| match set.decision() {
+                MultiDecision::Decided(tx) => tx,
+                MultiDecision::Undecided(_) => None,
+            })
+            .collect()
+    }
+
+    /// Whether every tracked conflict set is decided
+    pub fn is_decided(&self) -> bool {
+        self.pending_sets().next().is_none()
+    }
+
+    /// Node query for the next batched round
+    /// Sized after the biggest query among the undecided conflict sets.
+    pub fn node_query(&self) -> NodeQuery {
+        let mut node_query = self.node_query.clone();
+        if let Some(query_size) = self.pending_sets().map(|set| set.solver.query_size()).max() {
+            node_query.set_query_size(query_size);
+        }
+        node_query
+    }
+
+    /// Compute a single round for every undecided conflict set from batched responses
+    pub fn step(&mut self, responses: &[QueryResponse<Vec<Tx>>]) -> Result<(), ClaroError> {
+        let mut votes: Vec<Vec<(Option<Tx>, NodeWeight)>> = vec![Vec::new(); self.sets.len()];
+        for response in responses {
+            let preferred: &[Tx] = match response.as_vote() {
+                Some(Vote::Yes(preferred)) => preferred,
+                Some(Vote::No(_)) => &[],
+                None => continue,
+            };
+            let weight = self.configuration.weighting.weight(response)?;
+            // a responder votes once per conflict set, for the first of its txs it prefers if any
+            let mut set_votes: Vec<Option<Tx>> = vec![None; self.sets.len()];
+            for tx in preferred {
+                if let Some(&position) = self.txs.get(tx) {
+                    set_votes[position].get_or_insert_with(|| tx.clone());
+                }
+            }
+            for (votes, vote) in votes.iter_mut().zip(set_votes) {
+                votes.push((vote, weight));
+            }
+        }
+        for (set, votes) in self.sets.iter_mut().zip(votes) {
+            if set.decision().is_decided() {
+                continue;
+            }
+            set.solver.step_weighted(&votes)?;
+            debug!(target: CLARO_TARGET_TAG, txs = ?set.txs, decision = %set.decision());
+        }
+        Ok(())
+    }
+
+    /// Query every pending transaction in a single round and step on the responses
+    pub async fn query_round<Q>(&mut self, query: &mut Q) -> Result<(), ClaroError>
+    where
+        Q: VoteQuery<Tx = Vec<Tx>>,
+    {
+        let responses = query.query(&self.node_query(), self.pending()).await;
+        self.step(&responses)
+    }
+
+    fn pending_sets(&self) -> impl Iterator<Item = &ConflictSet<Tx>> {
+        self.sets.iter().filter(|set| !set.decision().is_decided())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::claro::Vote;
+    use crate::conflict::{ConflictSets, TxStatus};
+    use crate::query::{NodeQuery, QueryResponse, VoteQuery};
+    use crate::testing::configuration;
+    use crate::testing::query::responses;
+
+    /// Responders preferring every tx of the batch found in the inner list
+    struct PreferringQuery(Vec<u32>);
+
+    #[async_trait::async_trait]
+    impl VoteQuery for PreferringQuery {
+        type Tx = Vec<u32>;
+
+        async fn query(
+            &mut self,
+            node_query: &NodeQuery,
+            txs: Self::Tx,
+        ) -> Vec<QueryResponse<Self::Tx>> {
+            let preferred: Vec<u32> = txs.into_iter().filter(|tx| self.0.contains(tx)).collect();
+            responses(&vec![Vote::Yes(preferred); node_query.query_size()])
+        }
+    }
+
+    fn conflict_sets() -> ConflictSets<u32, &'static str> {
+        let configuration = configuration();
+        ConflictSets::new(configuration, NodeQuery::new(10, "node_1".into())).unwrap()
+    }
+
+    #[test]
+    fn first_tx_is_preferred() {
+        let mut sets = conflict_sets();
+        assert_eq!(sets.insert(1, "a").unwrap(), TxStatus::Preferred);
+        assert_eq!(sets.insert(2, "a").unwrap(), TxStatus::NotPreferred);
+        assert_eq!(sets.insert(3, "b").unwrap(), TxStatus::Preferred);
+        assert_eq!(sets.insert(1, "b").unwrap(), TxStatus::Preferred);
+        assert_eq!(sets.pending(), vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn accepts_one_tx_per_set() {
+        let mut sets = conflict_sets();
+        sets.insert(1, "a").unwrap();
+        sets.insert(2, "a").unwrap();
+        sets.insert(3, "b").unwrap();
+        sets.insert(4, "c").unwrap();
+        let mut query = PreferringQuery(vec![2, 3, 4]);
+        while !sets.is_decided() {
+            sets.query_round(&mut query).await.unwrap();
+        }
+        assert_eq!(sets.status(&1), Some(TxStatus::Rejected));
+        assert_eq!(sets.status(&2), Some(TxStatus::Accepted));
+        assert_eq!(sets.status(&3), Some(TxStatus::Accepted));
+        assert_eq!(sets.status(&4), Some(TxStatus::Accepted));
+        assert_eq!(sets.accepted(), vec![2, 3, 4]);
+        assert_eq!(sets.insert(5, "a").unwrap(), TxStatus::Rejected);
+    }
+
+    #[test]
+    fn minority_known_tx_is_not_accepted() {
+        let mut sets = conflict_sets();
+        sets.insert(1, "a").unwrap();
+        // only 2 out of 10 responders know about tx 1
+        let mut votes = vec![Vote::Yes(vec![1]); 2];
+        votes.extend(vec![Vote::Yes(vec![]); 8]);
+        while !sets.is_decided() {
+            sets.step(&responses(&votes)).unwrap();
+        }
+        assert_eq!(sets.status(&1), Some(TxStatus::Rejected));
+        assert!(sets.accepted().is_empty());
+    }
+}
@@ -0,0 +1,447 @@
+// std
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+// crates
+use tracing::debug;
+// internal
+use crate::claro::{weighted_votes, ClaroConfiguration, Vote};
+use crate::conflict::TxStatus;
+use crate::error::ClaroError;
+use crate::multi::{MultiClaroSolver, MultiDecision};
+use crate::query::{NodeQuery, NodeWeight, QueryResponse, VoteQuery};
+use crate::tracing::CLARO_TARGET_TAG;
+
+/// Single DAG vertex bookkeeping
+struct Vertex<Id, K> {
+    parents: Vec<Id>,
+    children: Vec<Id>,
+    conflict_set: K,
+    /// Whether the last query on this vertex ended preferring it
+    chit: bool,
+    /// Binary solver for the vertex, `true` stands for yes
+    solver: MultiClaroSolver<bool>,
+}
+
+/// DAG based Avalanche over Claro
+/// Vertices reference their parents and belong to a conflict set. Querying a vertex steps its
+/// own solver with every vote, while each [`Vote::Yes`] is also a yes vote for all of its
+/// ancestors, so evidence and confidence are accumulated transitively. Every queried vertex
+/// earns a chit when its round ends preferring it, and its Avalanche confidence is the number
+/// of chits in its progeny.
+/// At most one vertex per conflict set is accepted: the preferred one, once Claro decided it as
+/// yes and its progeny holds more chits than that of any conflicting vertex.
+pub struct Dag<Id, K> {
+    configuration: ClaroConfiguration,
+    node_query: NodeQuery,
+    vertices: HashMap<Id, Vertex<Id, K>>,
+    /// Vertex ids, in insertion order
+    order: Vec<Id>,
+    /// Vertex ids per conflict set, in insertion order
+    conflict_sets: HashMap<K, Vec<Id>>,
+    /// Accepted vertex per conflict set
+    accepted: HashMap<K, Id>,
+}
+
+impl<Id, K> Dag<Id, K>
+where
+    Id: Clone + Eq + Hash + Debug,
+    K: Clone + Eq + Hash,
+{
+    /// Fails if `configuration` does not [`ClaroConfiguration::validate`]
+    pub fn new(
+        configuration: ClaroConfiguration,
+        node_query: NodeQuery,
+    ) -> Result<Self, ClaroError> {
+        configuration.validate()?;
+        Ok(Self {
+            configuration,
+            node_query,
+            vertices: HashMap::new(),
+            order: Vec::new(),
+            conflict_sets: HashMap::new(),
+            accepted: HashMap::new(),
+        })
+    }
+
+    /// Add vertex `id` on top of `parents`, all of them must already be part of the DAG
+    /// Inserting an already known vertex is a no-op.
+    pub fn insert(&mut self, id: Id, parents: Vec<Id>, conflict_set: K) -> Result<(), ClaroError> {
+        if self.vertices.contains_key(&id) {
+            return Ok(());
+        }
+        if let Some(parent) = parents.iter().find(|p| !self.vertices.contains_key(p)) {
+            return Err(ClaroError::UnknownVertex(format!("{parent:?}")));
+        }
+        for parent in &parents {
+            self.vertex_mut(parent)?.children.push(id.clone());
+        }
+        self.conflict_sets
+            .entry(conflict_set.clone())
+            .or_default()
+            .push(id.clone());
+        let solver = MultiClaroSolver::new(
+            [true, false],
+            self.configuration,
+            self.node_query.clone(),
+            Some(true),
+        )?;
+        self.vertices.insert(
+            id.clone(),
+            Vertex {
+                parents,
+                children: Vec::new(),
+                conflict_set,
+                chit: false,
+                solver,
+            },
+        );
+        self.order.push(id);
+        Ok(())
+    }
+
+    /// Compute a single round for vertex `id` from already queried nodes responses
+    /// Yes votes are propagated to every undecided ancestor.
+    pub fn step(&mut self, id: &Id, responses: &[QueryResponse<Id>]) -> Result<(), ClaroError> {
+        let ancestors = self.ancestors(id)?;
+        let weighting = self.vertex(id)?.solver.configuration().weighting;
+        let votes = weighted_votes(&weighting, responses)?;
+        let yes_votes: Vec<(bool, NodeWeight)> =
+            votes.iter().copied().filter(|&(yes, _)| yes).collect();
+
+        let vertex = self.vertex_mut(id)?;
+        vertex.solver.step_weighted(&votes)?;
+        vertex.chit = vertex.solver.preference() == Some(true);
+        debug!(target: CLARO_TARGET_TAG, vertex = ?id, chit = vertex.chit, decision = %vertex.solver.decision());
+
+        if !yes_votes.is_empty() {
+            for ancestor in &ancestors {
+                let vertex = self.vertex_mut(ancestor)?;
+                if vertex.solver.decision().is_decided() {
+                    continue;
+                }
+                vertex.solver.step_weighted(&yes_votes)?;
+            }
+        }
+        // the chit of `id` counts towards the confidence of its whole lineage
+        for id in std::iter::once(id).chain(&ancestors) {
+            let conflict_set = self.vertices[id].conflict_set.clone();
+            self.settle(&conflict_set);
+        }
+        Ok(())
+    }
+
+    /// Query vertex `id` in a single round and step on the result
+    pub async fn query_round<Q>(&mut self, id: &Id, query: &mut Q) -> Result<(), ClaroError>
+    where
+        Q: VoteQuery<Tx = Id>,
+    {
+        let node_query = self.vertex(id)?.solver.node_query().clone();
+        let responses = query.query(&node_query, id.clone()).await;
+        self.step(id, &responses)
+    }
+
+    /// Vote to answer a query on `id` with, yes only if it is strongly preferred
+    pub fn vote(&self, id: &Id) -> Result<Vote<Id>, ClaroError> {
+        Ok(if self.is_strongly_preferred(id)? {
+            Vote::Yes(id.clone())
+        } else {
+            Vote::No(id.clone())
+        })
+    }
+
+    /// Whether `id` and all of its ancestors are the preferred vertex of their conflict sets
+    pub fn is_strongly_preferred(&self, id: &Id) -> Result<bool, ClaroError> {
+        let mut lineage = self.ancestors(id)?;
+        lineage.push(id.clone());
+        Ok(lineage.iter().all(|id| {
+            self.preferred(&self.vertices[id].conflict_set).as_ref() == Some(id)
+                && !self.is_rejected_locally(id)
+        }))
+    }
+
+    /// Preferred vertex of `conflict_set`
+    /// The accepted one if any, otherwise the yes preferring vertex with the highest confidence,
+    /// the first inserted one on ties.
+    pub fn preferred(&self, conflict_set: &K) -> Option<Id> {
+        if let Some(accepted) = self.accepted.get(conflict_set) {
+            return Some(accepted.clone());
+        }
+        let mut preferred: Option<(&Id, usize)> = None;
+        for id in self.conflict_sets.get(conflict_set)? {
+            let vertex = &self.vertices[id];
+            if vertex.solver.preference() != Some(true) {
+                continue;
+            }
+            let confidence = self.progeny_chits(id);
+            let better = match preferred {
+                Some((_, best)) => confidence > best,
+                None => true,
+            };
+            if better {
+                preferred = Some((id, confidence));
+            }
+        }
+        preferred.map(|(id, _)| id.clone())
+    }
+
+    /// Status of vertex `id`, `None` if it is not part of the DAG
+    pub fn status(&self, id: &Id) -> Option<TxStatus> {
+        let mut lineage = self.ancestors(id).ok()?;
+        lineage.push(id.clone());
+        let status = if lineage.iter().any(|id| self.is_rejected_locally(id)) {
+            TxStatus::Rejected
+        } else if lineage
+            .iter()
+            .all(|id| self.accepted.get(&self.vertices[id].conflict_set) == Some(id))
+        {
+            TxStatus::Accepted
+        } else if self.is_strongly_preferred(id).ok()? {
+            TxStatus::Preferred
+        } else {
+            TxStatus::NotPreferred
+        };
+        Some(status)
+    }
+
+    /// Avalanche confidence of `id`, the amount of chits in its progeny including itself
+    pub fn confidence(&self, id: &Id) -> Result<usize, ClaroError> {
+        self.vertex(id)?;
+        Ok(self.progeny_chits(id))
+    }
+
+    /// Whether the last query on `id` ended preferring it
+    pub fn chit(&self, id: &Id) -> Result<bool, ClaroError> {
+        Ok(self.vertex(id)?.chit)
+    }
+
+    /// Claro decision of vertex `id` alone, without conflicts or ancestry
+    pub fn decision(&self, id: &Id) -> Result<MultiDecision<bool>, ClaroError> {
+        Ok(self.vertex(id)?.solver.decision())
+    }
+
+    /// Vertices not decided yet, in insertion order
+    pub fn pending(&self) -> Vec<Id> {
+        self.order
+            .iter()
+            .filter(|id| !self.vertices[*id].solver.decision().is_decided())
+            .cloned()
+            .collect()
+    }
+
+    /// Ancestors of `id`, closest first, without duplicates
+    pub fn ancestors(&self, id: &Id) -> Result<Vec<Id>, ClaroError> {
+        let mut ancestors = Vec::new();
+        let mut seen = HashSet::new();
+        let mut next = self.vertex(id)?.parents.clone();
+        while !next.is_empty() {
+            let mut parents = Vec::new();
+            for parent in next {
+                if seen.insert(parent.clone()) {
+                    parents.extend(self.vertices[&parent].parents.iter().cloned());
+                    ancestors.push(parent);
+                }
+            }
+            next = parents;
+        }
+        Ok(ancestors)
+    }
+
+    /// Accept the preferred vertex of `conflict_set` once it is decided as yes and its progeny
+    /// holds more chits than that of every conflicting vertex
+    fn settle(&mut self, conflict_set: &K) {
+        if self.accepted.contains_key(conflict_set) {
+            return;
+        }
+        let Some(preferred) = self.preferred(conflict_set) else {
+            return;
+        };
+        if self.vertices[&preferred].solver.decision() != MultiDecision::Decided(Some(true)) {
+            return;
+        }
+        let confidence = self.progeny_chits(&preferred);
+        let leads = self.conflict_sets[conflict_set]
+            .iter()
+            .filter(|id| **id != preferred)
+            .all(|id| self.progeny_chits(id) < confidence);
+        if confidence > 0 && leads {
+            self.accepted.insert(conflict_set.clone(), preferred);
+        }
+    }
+
+    /// Decided as no, or another vertex of its conflict set was accepted
+    fn is_rejected_locally(&self, id: &Id) -> bool {
+        let vertex = &self.vertices[id];
+        vertex.solver.decision() == MultiDecision::Decided(Some(false))
+            || matches!(self.accepted.get(&vertex.conflict_set), Some(accepted) if accepted != id)
+    }
+
+    fn progeny_chits(&self, id: &Id) -> usize {
+        let mut seen = HashSet::new();
+        let mut next = vec![id.clone()];
+        let mut chits = 0;
+        while let Some(id) = next.pop() {
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            let vertex = &self.vertices[&id];
+            chits += usize::from(vertex.chit);
+            next.extend(vertex.children.iter().cloned());
+        }
+        chits
+    }
+
+    fn vertex(&self, id: &Id) -> Result<&Vertex<Id, K>, ClaroError> {
+        self.vertices
+            .get(id)
+            .ok_or_else(|| ClaroError::UnknownVertex(format!("{id:?}")))
+    }
+
+    fn vertex_mut(&mut self, id: &Id) -> Result<&mut Vertex<Id, K>, ClaroError> {
+        self.vertices
+            .get_mut(id)
+            .ok_or_else(|| ClaroError::UnknownVertex(format!("{id:?}")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::claro::{ClaroConfiguration, Vote};
+    use crate::conflict::TxStatus;
+    use crate::dag::Dag;
+    use crate::error::ClaroError;
+    use crate::multi::MultiDecision;
+    use crate::query::NodeQuery;
+    use crate::testing::configuration;
+    use crate::testing::query::*;
+
+    fn dag() -> Dag<&'static str, u8> {
+        let configuration = ClaroConfiguration {
+            confidence_beta: 0.5,
+            look_ahead: 10,
+            ..configuration()
+        };
+        Dag::new(configuration, NodeQuery::new(10, "node_1".into())).unwrap()
+    }
+
+    #[test]
+    fn yes_votes_are_transitive() {
+        let mut dag = dag();
+        dag.insert("genesis", vec![], 0).unwrap();
+        dag.insert("a", vec!["genesis"], 1).unwrap();
+        dag.insert("b", vec!["a"], 2).unwrap();
+        assert_eq!(dag.ancestors(&"b").unwrap(), vec!["a", "genesis"]);
+
+        let votes = responses(&[Vote::Yes("b"); 10]);
+        dag.step(&"b", &votes).unwrap();
+        assert!(dag.chit(&"b").unwrap());
+        assert!(!dag.chit(&"a").unwrap());
+        assert_eq!(dag.confidence(&"genesis").unwrap(), 1);
+        assert_eq!(dag.status(&"b"), Some(TxStatus::Preferred));
+
+        // the second round decides the whole chain, ancestors were never queried themselves
+        dag.step(&"b", &votes).unwrap();
+        for id in ["genesis", "a", "b"] {
+            assert_eq!(dag.status(&id), Some(TxStatus::Accepted));
+        }
+        assert!(dag.pending().is_empty());
+    }
+
+    #[test]
+    fn no_votes_are_not_transitive() {
+        let mut dag = dag();
+        dag.insert("genesis", vec![], 0).unwrap();
+        dag.insert("a", vec!["genesis"], 1).unwrap();
+        let votes = responses(&[Vote::No("a"); 10]);
+        dag.step(&"a", &votes).unwrap();
+        dag.step(&"a", &votes).unwrap();
+        assert_eq!(dag.status(&"a"), Some(TxStatus::Rejected));
+        assert_eq!(dag.status(&"genesis"), Some(TxStatus::Preferred));
+    }
+
+    #[test]
+    fn conflicting_ancestor_is_rejected() {
+        let mut dag = dag();
+        dag.insert("genesis", vec![], 0).unwrap();
+        dag.insert("a", vec!["genesis"], 1).unwrap();
+        dag.insert("a'", vec!["genesis"], 1).unwrap();
+        dag.insert("b", vec!["a"], 2).unwrap();
+        dag.insert("b'", vec!["a'"], 2).unwrap();
+        // most of the network sides with the branch of a, voting no on the other one
+        let mut votes = vec![Vote::Yes("b"); 7];
+        votes.extend(vec![Vote::No("b"); 3]);
+        let mut conflicting_votes = vec![Vote::Yes("b'"); 3];
+        conflicting_votes.extend(vec![Vote::No("b'"); 7]);
+        for _ in 0..20 {
+            let pending = dag.pending();
+            if pending.contains(&"b") {
+                dag.step(&"b", &responses(&votes)).unwrap();
+            }
+            if pending.contains(&"b'") {
+                dag.step(&"b'", &responses(&conflicting_votes)).unwrap();
+            }
+        }
+        assert_eq!(dag.status(&"a"), Some(TxStatus::Accepted));
+        assert_eq!(dag.status(&"a'"), Some(TxStatus::Rejected));
+        assert_eq!(dag.status(&"genesis"), Some(TxStatus::Accepted));
+    }
+
+    #[test]
+    fn frequently_queried_conflicting_ancestor_is_not_accepted() {
+        let mut dag = dag();
+        dag.insert("genesis", vec![], 0).unwrap();
+        dag.insert("a", vec!["genesis"], 1).unwrap();
+        dag.insert("a'", vec!["genesis"], 1).unwrap();
+        dag.insert("b", vec!["a"], 2).unwrap();
+        let mut votes = vec![Vote::Yes("b"); 7];
+        votes.extend(vec![Vote::No("b"); 3]);
+        // every child of a' is only backed by a few responders, but a' is queried far more
+        // often than a and ends up decided as yes by the transitive votes alone
+        let children = ["b'", "c'", "d'", "e'", "f'"];
+        for (set, child) in (2..).zip(children) {
+            dag.insert(child, vec!["a'"], set).unwrap();
+            let mut conflicting_votes = vec![Vote::Yes(child); 3];
+            conflicting_votes.extend(vec![Vote::No(child); 7]);
+            while dag.pending().contains(&child) {
+                dag.step(&child, &responses(&conflicting_votes)).unwrap();
+            }
+        }
+        assert_eq!(dag.decision(&"a'"), Ok(MultiDecision::Decided(Some(true))));
+        assert_eq!(dag.status(&"a'"), Some(TxStatus::NotPreferred));
+
+        while dag.pending().contains(&"b") {
+            dag.step(&"b", &responses(&votes)).unwrap();
+        }
+        assert_eq!(dag.status(&"a"), Some(TxStatus::Accepted));
+        assert_eq!(dag.status(&"a'"), Some(TxStatus::Rejected));
+    }
+
+    #[tokio::test]
+    async fn conflicts_accept_a_single_vertex() {
+        let mut dag = dag();
+        dag.insert("genesis", vec![], 0).unwrap();
+        dag.insert("a", vec!["genesis"], 1).unwrap();
+        dag.insert("a'", vec!["genesis"], 1).unwrap();
+        dag.insert("b", vec!["a'"], 2).unwrap();
+        assert_eq!(dag.preferred(&1), Some("a"));
+        assert_eq!(dag.vote(&"b"), Ok(Vote::No("b")));
+
+        let mut query = FixedQuery::new(Vote::Yes("a'"));
+        while dag.status(&"a'") != Some(TxStatus::Accepted) {
+            dag.query_round(&"a'", &mut query).await.unwrap();
+        }
+        assert_eq!(dag.status(&"a"), Some(TxStatus::Rejected));
+        assert_eq!(dag.preferred(&1), Some("a'"));
+        assert_eq!(dag.status(&"b"), Some(TxStatus::Preferred));
+        assert_eq!(dag.vote(&"b"), Ok(Vote::Yes("b")));
+    }
+
+    #[test]
+    fn unknown_parent_fails() {
+        let mut dag = dag();
+        assert_eq!(
+            dag.insert("a", vec!["genesis"], 1),
+            Err(ClaroError::UnknownVertex("\"genesis\"".to_string()))
+        );
+    }
+}
@@ -0,0 +1,268 @@
+// std
+use std::fmt::Debug;
+use std::future::Future;
+use std::time::Duration;
+// crates
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+// internal
+use crate::claro::{ClaroSolver, Decision};
+use crate::error::ClaroError;
+use crate::query::{QueryResponse, VoteQuery};
+use crate::solver::ConsensusSolver;
+use crate::tracing::CLARO_TARGET_TAG;
+
+/// Claro driver configuration
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClaroDriverConfiguration {
+    /// Max amount of query rounds to run, unbounded if `None`
+    pub max_rounds: Option<usize>,
+    /// Max time to wait for a single query to be answered, unbounded if `None`
+    pub query_timeout: Option<Duration>,
+}
+
+/// Single driver round record
+#[derive(Debug, Clone)]
+pub struct ClaroRound<Tx> {
+    /// Round number, starting at `0`
+    pub round: usize,
+    /// Responses received in this round, empty if the query timed out
+    pub responses: Vec<QueryResponse<Tx>>,
+    /// Whether the query timed out
+    pub timed_out: bool,
+    /// Solver decision after processing the round responses
+    pub decision: Decision<Tx>,
+}
+
+/// Reason why the driver stopped
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClaroDriverExit<E = ClaroError> {
+    /// The solver reached a decision
+    Decided,
+    /// [`ClaroDriverConfiguration::max_rounds`] was reached without a decision
+    MaxRounds,
+    /// The run was cancelled from the outside
+    Cancelled,
+    /// The solver failed to process a round
+    Failed(E),
+}
+
+/// Result of a full driver run
+#[derive(Debug, Clone)]
+pub struct ClaroDriverOutcome<Tx, E = ClaroError> {
+    /// Solver decision at the moment the driver stopped
+    pub decision: Decision<Tx>,
+    /// Reason why the driver stopped
+    pub exit: ClaroDriverExit<E>,
+    /// Per round records, in execution order
+    pub history: Vec<ClaroRound<Tx>>,
+}
+
+/// Claro decision loop runner
+/// Owns a solver, a [`ClaroSolver`] unless stated otherwise, and a [`VoteQuery`] and steps the
+/// former with the votes gathered by the latter until a decision is reached.
+/// Any [`ConsensusSolver`] can be driven.
+pub struct ClaroDriver<Q: VoteQuery, S = ClaroSolver<<Q as VoteQuery>::Tx>> {
+    solver: S,
+    query: Q,
+    configuration: ClaroDriverConfiguration,
+}
+
+impl<Q, S> ClaroDriver<Q, S>
+where
+    Q: VoteQuery,
+    Q::Tx: Clone + Debug,
+    S: ConsensusSolver<Q::Tx>,
+{
+    pub fn new(solver: S, query: Q, configuration: ClaroDriverConfiguration) -> Self {
+        Self {
+            solver,
+            query,
+            configuration,
+        }
+    }
+
+    /// Run the decision loop for `tx`
+    /// Stops when the solver is decided, the round limit is reached or `cancel` is triggered.
+    pub async fn run(
+        &mut self,
+        tx: Q::Tx,
+        cancel: CancellationToken,
+    ) -> ClaroDriverOutcome<Q::Tx, S::Error> {
+        let mut history = Vec::new();
+        let exit = loop {
+            if matches!(self.solver.decision(), Decision::Decided(_)) {
+                break ClaroDriverExit::Decided;
+            }
+            if let Some(max_rounds) = self.configuration.max_rounds {
+                if history.len() >= max_rounds {
+                    break ClaroDriverExit::MaxRounds;
+                }
+            }
+            let query = self.query.query(self.solver.node_query(), tx.clone());
+            let responses = tokio::select! {
+                biased;
+                _ = cancel.cancelled() => break ClaroDriverExit::Cancelled,
+                responses = with_timeout(query, self.configuration.query_timeout) => responses,
+            };
+            let timed_out = responses.is_none();
+            let responses = responses.unwrap_or_default();
+            if let Err(e) = self.solver.step(tx.clone(), &responses) {
+                break ClaroDriverExit::Failed(e);
+            }
+            let round = ClaroRound {
+                round: history.len(),
+                responses,
+                timed_out,
+                decision: self.solver.decision(),
+            };
+            debug!(target: CLARO_TARGET_TAG, round = round.round, timed_out, decision = %round.decision);
+            history.push(round);
+            // let other tasks, `cancel` included, run even if queries answer right away
+            tokio::task::yield_now().await;
+        };
+        ClaroDriverOutcome {
+            decision: self.solver.decision(),
+            exit,
+            history,
+        }
+    }
+
+    pub fn solver(&self) -> &S {
+        &self.solver
+    }
+
+    pub fn query(&self) -> &Q {
+        &self.query
+    }
+
+    /// Release the inner solver and query
+    pub fn into_inner(self) -> (S, Q) {
+        (self.solver, self.query)
+    }
+}
+
+/// Await `future`, giving up after `timeout` if any
+async fn with_timeout<F: Future>(future: F, timeout: Option<Duration>) -> Option<F::Output> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future).await.ok(),
+        None => Some(future.await),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::claro::{ClaroSolver, Vote};
+    use crate::driver::{ClaroDriver, ClaroDriverConfiguration, ClaroDriverExit};
+    use crate::query::{NodeQuery, QueryResponse, VoteQuery};
+    use crate::testing::configuration;
+    use crate::testing::query::*;
+    use crate::{Decision, Opinion};
+    use std::time::Duration;
+    use tokio_util::sync::CancellationToken;
+
+    struct SlowQuery(Duration);
+
+    #[async_trait::async_trait]
+    impl VoteQuery for SlowQuery {
+        type Tx = bool;
+
+        async fn query(
+            &mut self,
+            _node_query: &NodeQuery,
+            tx: Self::Tx,
+        ) -> Vec<QueryResponse<Self::Tx>> {
+            tokio::time::sleep(self.0).await;
+            responses(&[Vote::Yes(tx)])
+        }
+    }
+
+    fn solver() -> ClaroSolver<bool> {
+        let config = configuration();
+        let node_query = NodeQuery::new(config.query.query_size, "node_1".into());
+        ClaroSolver::with_initial_opinion(config, node_query, Opinion::None(true)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn runs_until_decided() {
+        let mut driver = ClaroDriver::new(
+            solver(),
+            FixedQuery::new(Vote::No(true)),
+            ClaroDriverConfiguration::default(),
+        );
+        let outcome = driver.run(true, CancellationToken::new()).await;
+        assert_eq!(outcome.exit, ClaroDriverExit::Decided);
+        assert_eq!(outcome.decision, Decision::Decided(Opinion::No(true)));
+        assert!(!outcome.history.is_empty());
+        assert_eq!(outcome.history.last().unwrap().decision, outcome.decision);
+    }
+
+    #[tokio::test]
+    async fn stops_at_max_rounds() {
+        let mut driver = ClaroDriver::new(
+            solver(),
+            NoQuery::default(),
+            ClaroDriverConfiguration {
+                max_rounds: Some(5),
+                query_timeout: None,
+            },
+        );
+        let outcome = driver.run(true, CancellationToken::new()).await;
+        assert_eq!(outcome.exit, ClaroDriverExit::MaxRounds);
+        assert_eq!(outcome.history.len(), 5);
+        assert!(matches!(outcome.decision, Decision::Undecided(_)));
+    }
+
+    #[tokio::test]
+    async fn cancelled_while_queries_answer_right_away() {
+        let mut driver = ClaroDriver::new(
+            solver(),
+            NoQuery::default(),
+            ClaroDriverConfiguration {
+                max_rounds: Some(10_000),
+                query_timeout: None,
+            },
+        );
+        let cancel = CancellationToken::new();
+        // single threaded runtime, only runs once the driver yields
+        tokio::spawn({
+            let cancel = cancel.clone();
+            async move { cancel.cancel() }
+        });
+        let outcome = driver.run(true, cancel).await;
+        assert_eq!(outcome.exit, ClaroDriverExit::Cancelled);
+        assert!(outcome.history.len() < 10_000);
+    }
+
+    #[tokio::test]
+    async fn query_timeout() {
+        let mut driver = ClaroDriver::new(
+            solver(),
+            SlowQuery(Duration::from_secs(10)),
+            ClaroDriverConfiguration {
+                max_rounds: Some(2),
+                query_timeout: Some(Duration::from_millis(1)),
+            },
+        );
+        let outcome = driver.run(true, CancellationToken::new()).await;
+        assert_eq!(outcome.exit, ClaroDriverExit::MaxRounds);
+        assert!(outcome
+            .history
+            .iter()
+            .all(|round| round.timed_out && round.responses.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn cancelled() {
+        let mut driver = ClaroDriver::new(
+            solver(),
+            SlowQuery(Duration::from_secs(10)),
+            ClaroDriverConfiguration::default(),
+        );
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let outcome = driver.run(true, cancel).await;
+        assert_eq!(outcome.exit, ClaroDriverExit::Cancelled);
+        assert!(outcome.history.is_empty());
+    }
+}
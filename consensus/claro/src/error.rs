@@ -0,0 +1,47 @@
+// std
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+// crates
+use rand::distributions::WeightedError;
+// internal
+use crate::query::NodeId;
+
+/// Claro solver and node query failures
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClaroError {
+    /// Solver was stepped after reaching a decision
+    AlreadyDecided,
+    /// Node is present in the sample population but has no weight
+    MissingWeight(NodeId),
+    /// Requested sample is bigger than the population to sample from
+    SampleTooLarge { requested: usize, available: usize },
+    /// Node weights cannot be used for weighted sampling (negative, NaN, all zero...)
+    InvalidWeights(WeightedError),
+    /// Configuration values are out of range or overflow
+    InvalidConfiguration(String),
+    /// Vertex is not part of the DAG, holds the vertex id debug representation
+    UnknownVertex(String),
+}
+
+impl Display for ClaroError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClaroError::AlreadyDecided => write!(f, "solver is already decided"),
+            ClaroError::MissingWeight(node_id) => write!(f, "missing weight for node {node_id}"),
+            ClaroError::SampleTooLarge {
+                requested,
+                available,
+            } => write!(
+                f,
+                "requested sample of {requested} nodes but only {available} are available"
+            ),
+            ClaroError::InvalidWeights(e) => write!(f, "invalid node weights: {e}"),
+            ClaroError::InvalidConfiguration(reason) => {
+                write!(f, "invalid configuration: {reason}")
+            }
+            ClaroError::UnknownVertex(id) => write!(f, "unknown vertex {id}"),
+        }
+    }
+}
+
+impl Error for ClaroError {}
@@ -1,12 +1,52 @@
 mod claro;
+mod conflict;
+mod dag;
+mod driver;
+mod error;
+mod multi;
 mod query;
+mod responder;
+mod round;
+mod solver;
+#[cfg(feature = "tcp")]
+mod tcp;
 mod tracing;
+#[cfg(feature = "tcp")]
+mod wire;
 
 #[cfg(feature = "testing")]
 pub mod testing;
 
 pub use self::claro::{
-    ClaroConfiguration, ClaroSolver, ClaroState, Decision, Opinion, QueryConfiguration, Vote,
+    BootstrapPolicy, ClaroConfiguration, ClaroSolver, Decision, EvidenceWeighting, Opinion,
+    QueryConfiguration, QueryGrowthPolicy, Vote,
+};
+pub use self::conflict::{ConflictSets, TxStatus};
+pub use self::dag::Dag;
+pub use self::driver::{
+    ClaroDriver, ClaroDriverConfiguration, ClaroDriverExit, ClaroDriverOutcome, ClaroRound,
+};
+pub use self::error::ClaroError;
+pub use self::multi::{
+    ClaroObservation, ClaroState, MultiClaroSnapshot, MultiClaroSolver, MultiDecision,
+};
+pub use self::query::{
+    NodeId, NodeQuery, NodeWeight, NodesSample, QueryResponse, ResponseMetadata, ResponseVote,
+    VoteQuery,
+};
+pub use self::responder::{RateLimit, Responder, UnknownTxPolicy, VoteAnswer, VoteRequest};
+pub use self::round::{
+    ClaroRoundCalculation, DecayRound, LinearRound, RoundComputation, RoundFormula, WindowedRound,
+};
+pub use self::solver::ConsensusSolver;
+#[cfg(feature = "tcp")]
+pub use self::tcp::{TcpPeer, TcpVoteQuery, TcpVoteServer};
+pub use self::tracing::{
+    claro_tracing_layer_with_writer, ChromeTraceGuard, ChromeTraceLayer, ClaroMetrics,
+    ClaroMetricsLayer, CLARO_ROUND_SPAN, CLARO_SOLVER_SPAN, CLARO_TARGET_TAG,
+};
+#[cfg(feature = "tcp")]
+pub use self::wire::{
+    decode_answer, decode_request, encode_answer, encode_request, read_frame, write_frame, WireTx,
+    MAX_FRAME_SIZE,
 };
-pub use self::query::{NodeId, NodeQuery, NodeWeight, NodesSample, VoteQuery};
-pub use self::tracing::{claro_tracing_layer_with_writer, CLARO_TARGET_TAG};
@@ -0,0 +1,612 @@
+// std
+use std::collections::VecDeque;
+use std::fmt::{Debug, Display, Formatter};
+// crates
+use tracing::{debug, debug_span, field, Span};
+// internal
+use crate::claro::ClaroConfiguration;
+use crate::error::ClaroError;
+use crate::query::{NodeQuery, NodeWeight};
+use crate::round::{ClaroRoundCalculation, RoundComputation, RoundFormula};
+use crate::tracing::{CLARO_ROUND_SPAN, CLARO_SOLVER_SPAN, CLARO_TARGET_TAG};
+
+/// Decision over a conflict set, holding the preferred value if any
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MultiDecision<V> {
+    Decided(Option<V>),
+    Undecided(Option<V>),
+}
+
+impl<V> MultiDecision<V> {
+    /// Currently preferred value, `None` if no opinion was formed yet
+    pub fn preference(&self) -> Option<&V> {
+        match self {
+            MultiDecision::Decided(preference) | MultiDecision::Undecided(preference) => {
+                preference.as_ref()
+            }
+        }
+    }
+
+    pub fn into_preference(self) -> Option<V> {
+        match self {
+            MultiDecision::Decided(preference) | MultiDecision::Undecided(preference) => preference,
+        }
+    }
+
+    pub fn is_decided(&self) -> bool {
+        matches!(self, MultiDecision::Decided(_))
+    }
+}
+
+impl<V> Display for MultiDecision<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let tag = match self {
+            MultiDecision::Decided(_) => "decided",
+            MultiDecision::Undecided(_) => "undecided",
+        };
+        write!(f, "{}", tag)
+    }
+}
+
+/// Claro internal state
+/// Evidence is tracked per conflict set value, in the order values were first known.
+/// Every vote adds up its weight, `1.0` unless weighted by stake, see
+/// [`crate::EvidenceWeighting`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClaroState<V = bool> {
+    /// Votes weight seen per conflict set value
+    evidence: Vec<(V, NodeWeight)>,
+    /// Total votes weight seen, for any value
+    evidence_accumulated: NodeWeight,
+    /// Votes ratio
+    confidence: NodeWeight,
+    /// Latest rounds votes, only kept by windowed round computations
+    #[cfg_attr(feature = "serde", serde(default = "VecDeque::new"))]
+    window: VecDeque<Vec<(V, NodeWeight)>>,
+}
+
+impl<V> Default for ClaroState<V> {
+    fn default() -> Self {
+        Self {
+            evidence: Vec::new(),
+            evidence_accumulated: 0.0,
+            confidence: 0.0,
+            window: VecDeque::new(),
+        }
+    }
+}
+
+impl<V: Clone + PartialEq> ClaroState<V> {
+    /// Empty state over an already known conflict set
+    pub fn with_values(values: impl IntoIterator<Item = V>) -> Self {
+        let mut state = Self::default();
+        for value in values {
+            state.evidence_entry(&value);
+        }
+        state
+    }
+
+    /// Learn a conflict set value, no-op if already known
+    pub fn insert_value(&mut self, value: &V) {
+        self.evidence_entry(value);
+    }
+
+    fn evidence_entry(&mut self, value: &V) -> &mut NodeWeight {
+        let position = match self.evidence.iter().position(|(v, _)| v == value) {
+            Some(position) => position,
+            None => {
+                self.evidence.push((value.clone(), 0.0));
+                self.evidence.len() - 1
+            }
+        };
+        &mut self.evidence[position].1
+    }
+
+    /// Add up the weight of every vote
+    pub fn update_confidence(&mut self, votes: &[(V, NodeWeight)]) {
+        self.confidence += total_weight(votes);
+    }
+
+    /// Add up the weight of every vote to the evidence of the voted value
+    pub fn update_evidence(&mut self, votes: &[(V, NodeWeight)]) {
+        for (vote, weight) in votes {
+            *self.evidence_entry(vote) += weight;
+        }
+        self.evidence_accumulated += total_weight(votes);
+    }
+
+    /// Take back the weight of every vote from the evidence of the voted value
+    pub fn retract_evidence(&mut self, votes: &[(V, NodeWeight)]) {
+        for (vote, weight) in votes {
+            let evidence = self.evidence_entry(vote);
+            *evidence = (*evidence - weight).max(0.0);
+        }
+        self.evidence_accumulated = (self.evidence_accumulated - total_weight(votes)).max(0.0);
+    }
+
+    /// Scale every value evidence, and the accumulated one, by `factor`
+    pub fn scale_evidence(&mut self, factor: NodeWeight) {
+        for (_, evidence) in &mut self.evidence {
+            *evidence *= factor;
+        }
+        self.evidence_accumulated *= factor;
+    }
+
+    /// Keep `votes` as the latest round of a `size` rounds window, returning the rounds that
+    /// no longer fit in it
+    pub fn push_window(
+        &mut self,
+        votes: &[(V, NodeWeight)],
+        size: usize,
+    ) -> Vec<Vec<(V, NodeWeight)>> {
+        self.window.push_back(votes.to_vec());
+        let expired = self.window.len().saturating_sub(size);
+        self.window.drain(..expired).collect()
+    }
+
+    pub fn confidence(&self) -> NodeWeight {
+        self.confidence
+    }
+
+    /// Votes weight seen for `value`
+    pub fn evidence_for(&self, value: &V) -> NodeWeight {
+        self.evidence
+            .iter()
+            .find_map(|(v, evidence)| (v == value).then_some(*evidence))
+            .unwrap_or_default()
+    }
+
+    pub fn evidence_accumulated(&self) -> NodeWeight {
+        self.evidence_accumulated
+    }
+
+    /// Known conflict set values, in the order they were first seen
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.evidence.iter().map(|(value, _)| value)
+    }
+}
+
+impl ClaroState<bool> {
+    /// Positive votes weight seen
+    pub fn evidence(&self) -> NodeWeight {
+        self.evidence_for(&true)
+    }
+}
+
+fn total_weight<V>(votes: &[(V, NodeWeight)]) -> NodeWeight {
+    votes.iter().map(|(_, weight)| weight).sum()
+}
+
+fn solver_span(node_query: &NodeQuery) -> Span {
+    debug_span!(target: CLARO_TARGET_TAG, CLARO_SOLVER_SPAN, node_id = %node_query.node_id())
+}
+
+fn round_span(solver: &Span, round: usize) -> Span {
+    debug_span!(
+        target: CLARO_TARGET_TAG,
+        parent: solver,
+        CLARO_ROUND_SPAN,
+        round,
+        e = field::Empty,
+        alpha = field::Empty,
+        confidence = field::Empty,
+        query_grew = field::Empty,
+        query_size = field::Empty,
+        decided = field::Empty,
+        preference = field::Empty,
+    )
+}
+
+fn value_weight<V: PartialEq>(votes: &[(V, NodeWeight)], value: &V) -> NodeWeight {
+    votes
+        .iter()
+        .filter(|(v, _)| v == value)
+        .map(|(_, weight)| weight)
+        .sum()
+}
+
+/// Record of a single [`MultiClaroSolver`] round
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClaroObservation<V> {
+    /// Round votes weight per known conflict set value
+    pub tally: Vec<(V, NodeWeight)>,
+    /// Round computation per known conflict set value, empty if the round had no votes
+    pub calculations: Vec<(V, ClaroRoundCalculation)>,
+    /// Whether no value went over alpha, growing the query
+    pub query_grew: bool,
+    /// Query size for the next round
+    pub query_size: usize,
+    /// Decision after the round
+    pub decision: MultiDecision<V>,
+}
+
+/// [`MultiClaroSolver`] in-flight state, see [`MultiClaroSolver::snapshot`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultiClaroSnapshot<V> {
+    pub state: ClaroState<V>,
+    /// Configuration, including the query size grown so far
+    pub configuration: ClaroConfiguration,
+    pub decision: MultiDecision<V>,
+    pub node_query: NodeQuery,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rounds: usize,
+}
+
+/// Claro computation object over a conflict set of `k` values
+/// Each round, the first known value whose evidence goes over alpha becomes the preference.
+/// Rounds are computed by `R`, the [`ClaroConfiguration::formula`] preset unless built
+/// [`MultiClaroSolver::with_round_computation`].
+pub struct MultiClaroSolver<V, R = RoundFormula> {
+    /// Internal state
+    state: ClaroState<V>,
+    /// Configuration, including node query configuration
+    configuration: ClaroConfiguration,
+    /// Current conflict set decision
+    decision: MultiDecision<V>,
+    /// Node query setup for current node
+    node_query: NodeQuery,
+    /// Rounds computed so far
+    rounds: usize,
+    /// Parent span of every round span
+    span: Span,
+    round_computation: R,
+}
+
+impl<V: Clone + PartialEq + Debug> MultiClaroSolver<V> {
+    /// Build a solver over `conflict_set`, values not in it are learned as votes arrive
+    /// Fails if `configuration` does not [`ClaroConfiguration::validate`].
+    pub fn new(
+        conflict_set: impl IntoIterator<Item = V>,
+        configuration: ClaroConfiguration,
+        node_query: NodeQuery,
+        preference: Option<V>,
+    ) -> Result<Self, ClaroError> {
+        Self::with_round_computation(
+            conflict_set,
+            configuration,
+            node_query,
+            preference,
+            configuration.formula,
+        )
+    }
+
+    /// Resume a solver from a previously taken snapshot
+    /// Fails if the snapshot configuration does not [`ClaroConfiguration::validate`]
+    pub fn restore(snapshot: MultiClaroSnapshot<V>) -> Result<Self, ClaroError> {
+        let formula = snapshot.configuration.formula;
+        Self::restore_with_round_computation(snapshot, formula)
+    }
+}
+
+impl<V: Clone + PartialEq + Debug, R: RoundComputation> MultiClaroSolver<V, R> {
+    /// Same as [`MultiClaroSolver::new`], computing rounds with `round_computation` instead of the
+    /// [`ClaroConfiguration::formula`] preset
+    pub fn with_round_computation(
+        conflict_set: impl IntoIterator<Item = V>,
+        configuration: ClaroConfiguration,
+        mut node_query: NodeQuery,
+        preference: Option<V>,
+        round_computation: R,
+    ) -> Result<Self, ClaroError> {
+        configuration.validate()?;
+        node_query.set_query_size(configuration.query.query_size);
+        Ok(Self {
+            state: ClaroState::with_values(conflict_set),
+            decision: MultiDecision::Undecided(preference),
+            configuration,
+            span: solver_span(&node_query),
+            node_query,
+            rounds: 0,
+            round_computation,
+        })
+    }
+
+    /// Same as [`MultiClaroSolver::restore`], computing rounds with `round_computation`
+    pub fn restore_with_round_computation(
+        snapshot: MultiClaroSnapshot<V>,
+        round_computation: R,
+    ) -> Result<Self, ClaroError> {
+        let MultiClaroSnapshot {
+            state,
+            configuration,
+            decision,
+            node_query,
+            rounds,
+        } = snapshot;
+        configuration.validate()?;
+        Ok(Self {
+            state,
+            configuration,
+            decision,
+            span: solver_span(&node_query),
+            node_query,
+            rounds,
+            round_computation,
+        })
+    }
+
+    /// Copy of the in-flight state, enough to [`MultiClaroSolver::restore`] it later on
+    pub fn snapshot(&self) -> MultiClaroSnapshot<V> {
+        MultiClaroSnapshot {
+            state: self.state.clone(),
+            configuration: self.configuration,
+            decision: self.decision.clone(),
+            node_query: self.node_query.clone(),
+            rounds: self.rounds,
+        }
+    }
+
+    /// Learn a new conflict set value, the current preference is kept
+    pub fn insert_value(&mut self, value: &V) {
+        self.state.insert_value(value);
+    }
+
+    /// Compute a single round, every vote weighting `1.0`
+    /// mutates the decision parameter upon this round data
+    pub fn step(&mut self, votes: &[V]) -> Result<ClaroObservation<V>, ClaroError> {
+        let votes: Vec<(V, NodeWeight)> = votes.iter().map(|vote| (vote.clone(), 1.0)).collect();
+        self.step_weighted(&votes)
+    }
+
+    /// Compute a single round from votes along with their weight
+    /// Nodes without a preference first try to adopt one following [`crate::BootstrapPolicy`],
+    /// rounds with no weight at all, or still without a preference, do nothing else.
+    pub fn step_weighted(
+        &mut self,
+        votes: &[(V, NodeWeight)],
+    ) -> Result<ClaroObservation<V>, ClaroError> {
+        if self.decision.is_decided() {
+            return Err(ClaroError::AlreadyDecided);
+        }
+        let span = round_span(&self.span, self.rounds);
+        let _entered = span.enter();
+        self.rounds += 1;
+        let observation = self.round(votes)?;
+        let preferred = observation
+            .calculations
+            .iter()
+            .find(|(value, _)| observation.decision.preference() == Some(value));
+        if let Some((_, calculation)) = preferred {
+            span.record("e", calculation.e);
+            span.record("alpha", calculation.alpha);
+            span.record("confidence", calculation.confidence);
+        }
+        span.record("query_grew", observation.query_grew);
+        span.record("query_size", observation.query_size);
+        span.record("decided", observation.decision.is_decided());
+        span.record(
+            "preference",
+            field::debug(observation.decision.preference()),
+        );
+        Ok(observation)
+    }
+
+    fn round(&mut self, votes: &[(V, NodeWeight)]) -> Result<ClaroObservation<V>, ClaroError> {
+        debug!(target: CLARO_TARGET_TAG, votes = ?votes);
+        if let MultiDecision::Undecided(None) = self.decision {
+            let quorum = self.configuration.evidence_alpha as NodeWeight * total_weight(votes);
+            if let Some(value) = self.configuration.bootstrap.adopt(votes, quorum) {
+                self.decision = MultiDecision::Undecided(Some(value));
+            }
+        }
+
+        let mut calculations = Vec::new();
+        let mut query_grew = false;
+        // no confidence is built up before having a preference to be confident about
+        let preferring = self.decision.preference().is_some();
+        if preferring && total_weight(votes) > 0.0 {
+            let formula = &self.round_computation;
+            formula.update(&mut self.state, votes);
+
+            for value in self.state.values() {
+                let calculation = formula.round(&self.configuration, &self.state, votes, value);
+                debug!(
+                    target: CLARO_TARGET_TAG,
+                    value = ?value,
+                    e = calculation.e,
+                    alpha = calculation.alpha
+                );
+                calculations.push((value.clone(), calculation));
+            }
+            let preferred = calculations
+                .iter()
+                .find(|(_, calculation)| calculation.e > calculation.alpha);
+            match preferred {
+                Some((value, _)) => {
+                    self.decision = MultiDecision::Undecided(Some(value.clone()));
+                    self.configuration.query.relax();
+                }
+                None => {
+                    self.configuration.query.grow()?;
+                    query_grew = true;
+                }
+            }
+            self.node_query
+                .set_query_size(self.configuration.query.query_size);
+            let confidence = calculations
+                .last()
+                .map(|(_, calculation)| calculation.confidence)
+                .unwrap_or_default();
+            if confidence > self.configuration.confidence_beta {
+                self.decision = MultiDecision::Decided(self.preference());
+            }
+        }
+        Ok(ClaroObservation {
+            tally: self
+                .state
+                .values()
+                .map(|value| (value.clone(), value_weight(votes, value)))
+                .collect(),
+            calculations,
+            query_grew,
+            query_size: self.configuration.query.query_size,
+            decision: self.decision.clone(),
+        })
+    }
+
+    /// Derive vote from it's current decision
+    pub fn vote(&self) -> Option<V> {
+        self.preference()
+    }
+
+    pub fn decision(&self) -> MultiDecision<V> {
+        self.decision.clone()
+    }
+
+    pub fn preference(&self) -> Option<V> {
+        self.decision.preference().cloned()
+    }
+
+    pub fn state(&self) -> &ClaroState<V> {
+        &self.state
+    }
+
+    pub fn configuration(&self) -> &ClaroConfiguration {
+        &self.configuration
+    }
+
+    pub fn node_query(&self) -> &NodeQuery {
+        &self.node_query
+    }
+
+    /// Current effective query size, including any growth applied so far
+    pub fn query_size(&self) -> usize {
+        self.configuration.query.query_size
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::claro::{BootstrapPolicy, ClaroConfiguration};
+    use crate::multi::{ClaroState, MultiClaroSolver, MultiDecision};
+    use crate::query::{NodeQuery, NodeWeight};
+    use crate::round::{ClaroRoundCalculation, LinearRound, RoundComputation};
+
+    fn configuration() -> ClaroConfiguration {
+        ClaroConfiguration {
+            evidence_alpha: 0.6,
+            evidence_alpha_2: 0.6,
+            confidence_beta: 0.5,
+            look_ahead: 10,
+            ..crate::testing::configuration()
+        }
+    }
+
+    #[test]
+    fn adopts_majority_colour() {
+        let node_query = NodeQuery::new(10, "node_1".into());
+        let mut solver = MultiClaroSolver::new(
+            ["red", "green", "blue"],
+            configuration(),
+            node_query,
+            Some("red"),
+        )
+        .unwrap();
+        let votes = [
+            "blue", "blue", "blue", "blue", "blue", "blue", "blue", "green", "red", "red",
+        ];
+        solver.step(&votes).unwrap();
+        assert_eq!(solver.decision(), MultiDecision::Undecided(Some("blue")));
+        assert_eq!(solver.state().evidence_for(&"blue"), 7.0);
+        assert_eq!(solver.state().evidence_for(&"green"), 1.0);
+        assert_eq!(solver.state().evidence_accumulated(), 10.0);
+
+        solver.step(&votes).unwrap();
+        assert_eq!(solver.decision(), MultiDecision::Decided(Some("blue")));
+    }
+
+    #[test]
+    fn grows_without_majority() {
+        let node_query = NodeQuery::new(10, "node_1".into());
+        let mut solver =
+            MultiClaroSolver::new([1u8, 2, 3], configuration(), node_query, Some(1)).unwrap();
+        solver.step(&[1, 1, 1, 2, 2, 2, 3, 3, 3, 3]).unwrap();
+        assert_eq!(solver.decision(), MultiDecision::Undecided(Some(1)));
+        assert_eq!(solver.query_size(), 20);
+    }
+
+    #[test]
+    fn stake_outweighs_count() {
+        let node_query = NodeQuery::new(10, "node_1".into());
+        let mut solver =
+            MultiClaroSolver::new(["red", "blue"], configuration(), node_query, Some("red"))
+                .unwrap();
+        // a single heavy responder against three light ones
+        let votes = [("blue", 10.0), ("red", 1.0), ("red", 1.0), ("red", 1.0)];
+        solver.step_weighted(&votes).unwrap();
+        assert_eq!(solver.preference(), Some("blue"));
+        assert_eq!(solver.state().evidence_for(&"blue"), 10.0);
+        assert_eq!(solver.state().confidence(), 13.0);
+    }
+
+    #[test]
+    fn learns_unknown_values() {
+        let node_query = NodeQuery::new(10, "node_1".into());
+        let mut solver = MultiClaroSolver::new([], configuration(), node_query, None).unwrap();
+        solver.step(&[7u64; 10]).unwrap();
+        assert_eq!(solver.preference(), Some(7));
+        assert_eq!(solver.state().values().collect::<Vec<_>>(), vec![&7]);
+    }
+
+    /// Only the latest round votes count, confidence never builds up
+    struct LatestRound;
+
+    impl RoundComputation for LatestRound {
+        fn round<V: Clone + PartialEq>(
+            &self,
+            configuration: &ClaroConfiguration,
+            state: &ClaroState<V>,
+            votes: &[(V, NodeWeight)],
+            value: &V,
+        ) -> ClaroRoundCalculation {
+            let e = LinearRound.round(configuration, state, votes, value).e1;
+            ClaroRoundCalculation {
+                confidence: 0.0,
+                e1: e,
+                e2: e,
+                e,
+                alpha: configuration.evidence_alpha,
+            }
+        }
+    }
+
+    #[test]
+    fn custom_round_computation() {
+        let node_query = NodeQuery::new(10, "node_1".into());
+        let mut solver = MultiClaroSolver::with_round_computation(
+            ["red", "blue"],
+            configuration(),
+            node_query,
+            Some("red"),
+            LatestRound,
+        )
+        .unwrap();
+        for _ in 0..5 {
+            solver.step(&["blue"; 10]).unwrap();
+        }
+        assert_eq!(solver.decision(), MultiDecision::Undecided(Some("blue")));
+        solver.step(&["red"; 10]).unwrap();
+        assert_eq!(solver.decision(), MultiDecision::Undecided(Some("red")));
+    }
+
+    #[test]
+    fn no_confidence_without_quorum() {
+        let configuration = ClaroConfiguration {
+            bootstrap: BootstrapPolicy::Quorum,
+            ..configuration()
+        };
+        let node_query = NodeQuery::new(10, "node_1".into());
+        let mut solver =
+            MultiClaroSolver::new([1u8, 2, 3], configuration, node_query, None).unwrap();
+        // no value ever gets the 0.6 quorum
+        for _ in 0..50 {
+            solver.step(&[1, 1, 1, 2, 2, 2, 3, 3, 3, 3]).unwrap();
+        }
+        assert_eq!(solver.decision(), MultiDecision::Undecided(None));
+        assert_eq!(solver.state().confidence(), 0.0);
+    }
+}
@@ -1,7 +1,11 @@
 use crate::claro::Vote;
+use crate::error::ClaroError;
+use crate::tracing::CLARO_TARGET_TAG;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{thread_rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use std::collections::HashMap;
+use std::time::Duration;
 use tracing::debug;
 
 // TODO: Check on proper types
@@ -19,6 +23,7 @@ pub trait NodesSample {
 
 /// Selector of nodes, random sample for some size `K`
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NodeQuery {
     node_size: usize,
     node_id: NodeId,
@@ -33,39 +38,177 @@ impl NodeQuery {
         self.node_size
     }
 
+    pub fn set_query_size(&mut self, node_size: usize) {
+        self.node_size = node_size;
+    }
+
     pub fn node_id(&self) -> &NodeId {
         &self.node_id
     }
 
-    pub fn sample<Sample: NodesSample>(&self, node_sample: &Sample) -> Vec<NodeId> {
-        let node_ids = node_sample.nodes();
+    /// Weighted sample of nodes ids, excluding the current node id
+    pub fn sample<Sample: NodesSample>(
+        &self,
+        node_sample: &Sample,
+    ) -> Result<Vec<NodeId>, ClaroError> {
+        self.sample_with_rng(node_sample, &mut thread_rng())
+    }
+
+    /// Reproducible version of [`NodeQuery::sample`], seeded from the node id and `round`
+    pub fn sample_for_round<Sample: NodesSample>(
+        &self,
+        node_sample: &Sample,
+        round: u64,
+    ) -> Result<Vec<NodeId>, ClaroError> {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.round_seed(round));
+        self.sample_with_rng(node_sample, &mut rng)
+    }
+
+    /// [`NodeQuery::sample`] drawing randomness from `rng`
+    /// Same `rng` state and same `node_sample` nodes yield the same sample, whatever order
+    /// [`NodesSample::nodes`] lists them in.
+    pub fn sample_with_rng<Sample: NodesSample, Rng: RngCore>(
+        &self,
+        node_sample: &Sample,
+        rng: &mut Rng,
+    ) -> Result<Vec<NodeId>, ClaroError> {
+        let mut node_ids = node_sample.nodes();
+        node_ids.sort_unstable();
         let weights = node_sample.weights();
-        // TODO: do we need to be reproducible?
-        let mut rng = thread_rng();
-        let node_ids = node_ids
-            .as_slice()
-            .choose_multiple_weighted(&mut rng, self.node_size + 1, |e| *weights.get(e).unwrap())
-            .unwrap()
-            .cloned()
-            .filter(|node_id| node_id != &self.node_id)
+        let weighted_ids: Vec<(&NodeId, NodeWeight)> = node_ids
+            .iter()
+            .map(|node_id| {
+                weights
+                    .get(node_id)
+                    .map(|&weight| (node_id, weight))
+                    .ok_or_else(|| ClaroError::MissingWeight(node_id.clone()))
+            })
+            .collect::<Result<_, _>>()?;
+        let available = node_ids
+            .iter()
+            .filter(|&node_id| node_id != &self.node_id)
+            .count();
+        if self.node_size > available {
+            return Err(ClaroError::SampleTooLarge {
+                requested: self.node_size,
+                available,
+            });
+        }
+        let node_ids: Vec<NodeId> = weighted_ids
+            .choose_multiple_weighted(rng, self.node_size + 1, |(_, weight)| *weight)
+            .map_err(ClaroError::InvalidWeights)?
+            .map(|(node_id, _)| *node_id)
+            .filter(|&node_id| node_id != &self.node_id)
             .take(self.node_size)
+            .cloned()
             .collect();
-        debug!(query_node_ids = ?node_ids);
-        node_ids
+        debug!(target: CLARO_TARGET_TAG, query_node_ids = ?node_ids);
+        Ok(node_ids)
+    }
+
+    /// Copy of this query asking at most every node of `node_sample` but the current one
+    /// Opt-in for callers that would rather query fewer nodes than get
+    /// [`ClaroError::SampleTooLarge`].
+    pub fn clamped<Sample: NodesSample>(&self, node_sample: &Sample) -> Self {
+        let available = node_sample
+            .nodes()
+            .iter()
+            .filter(|&node_id| node_id != &self.node_id)
+            .count();
+        if self.node_size > available {
+            debug!(
+                target: CLARO_TARGET_TAG,
+                requested = self.node_size,
+                available,
+                "query size over the available nodes, sampling all of them"
+            );
+        }
+        Self {
+            node_size: self.node_size.min(available),
+            node_id: self.node_id.clone(),
+        }
+    }
+
+    /// Stable seed for a node id and round pair (FNV-1a over both)
+    pub fn round_seed(&self, round: u64) -> u64 {
+        const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
+        self.node_id
+            .as_bytes()
+            .iter()
+            .chain(round.to_le_bytes().iter())
+            .fold(FNV_OFFSET, |hash, &byte| {
+                (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+            })
+    }
+}
+
+/// What a queried node answered
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ResponseVote<Tx> {
+    Vote(Vote<Tx>),
+    /// The node abstained or its answer was lost
+    Missing,
+    /// The node did not answer in time
+    TimedOut,
+}
+
+/// Optional information attached to a response by the communication layer
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResponseMetadata {
+    /// Stake of the responder
+    pub weight: Option<NodeWeight>,
+    /// Time it took to get the answer
+    pub latency: Option<Duration>,
+}
+
+/// Single node entry of a query result
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryResponse<Tx> {
+    pub node_id: NodeId,
+    pub vote: ResponseVote<Tx>,
+    pub metadata: ResponseMetadata,
+}
+
+impl<Tx> QueryResponse<Tx> {
+    pub fn new(node_id: NodeId, vote: ResponseVote<Tx>) -> Self {
+        Self {
+            node_id,
+            vote,
+            metadata: ResponseMetadata::default(),
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: ResponseMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Vote of the responder, `None` if it is missing or timed out
+    pub fn as_vote(&self) -> Option<&Vote<Tx>> {
+        match &self.vote {
+            ResponseVote::Vote(vote) => Some(vote),
+            ResponseVote::Missing | ResponseVote::TimedOut => None,
+        }
     }
 }
 
 /// Communication layer abstraction trait
-/// Used by the claro algorithm runner to query for the votes of other nodes
+/// Used by the claro algorithm runner to query for the votes of other nodes.
+/// Returns one [`QueryResponse`] per queried node.
 #[async_trait::async_trait]
 pub trait VoteQuery: Send + Sync {
     type Tx;
-    async fn query(&mut self, node_query: &NodeQuery, tx: Self::Tx) -> Vec<Vote<Self::Tx>>;
+    async fn query(&mut self, node_query: &NodeQuery, tx: Self::Tx)
+        -> Vec<QueryResponse<Self::Tx>>;
 }
 
 #[cfg(test)]
 mod test {
+    use crate::error::ClaroError;
     use crate::query::{NodeId, NodeQuery, NodeWeight, NodesSample};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
     use std::collections::{HashMap, HashSet};
 
     struct TestSample {
@@ -101,7 +244,107 @@ mod test {
             node_weights: (1..11usize).map(|i| i as f64).collect(),
         };
 
-        let ids: HashSet<_> = query.sample(&sample).into_iter().collect();
+        let ids: HashSet<_> = query.sample(&sample).unwrap().into_iter().collect();
         assert_eq!(ids.len(), sample.len());
     }
+
+    fn test_sample() -> TestSample {
+        TestSample {
+            node_ids: (0..100).map(|i| i.to_string()).collect(),
+            node_weights: (1..101usize).map(|i| i as f64).collect(),
+        }
+    }
+
+    #[test]
+    fn same_rng_seed_same_sample() {
+        let query: NodeQuery = NodeQuery::new(10, "0".into());
+        let sample = test_sample();
+
+        let first = query
+            .sample_with_rng(&sample, &mut StdRng::seed_from_u64(18042022))
+            .unwrap();
+        let second = query
+            .sample_with_rng(&sample, &mut StdRng::seed_from_u64(18042022))
+            .unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.len(), 10);
+        assert!(!first.contains(query.node_id()));
+    }
+
+    #[test]
+    fn round_sample_is_reproducible() {
+        let query: NodeQuery = NodeQuery::new(10, "0".into());
+        let other_query: NodeQuery = NodeQuery::new(10, "1".into());
+        let sample = test_sample();
+
+        assert_eq!(
+            query.sample_for_round(&sample, 7).unwrap(),
+            query.sample_for_round(&sample, 7).unwrap()
+        );
+        assert_ne!(query.round_seed(7), query.round_seed(8));
+        assert_ne!(query.round_seed(7), other_query.round_seed(7));
+    }
+
+    #[test]
+    fn round_sample_ignores_nodes_order() {
+        let query: NodeQuery = NodeQuery::new(10, "0".into());
+        let sample = test_sample();
+        let reversed = TestSample {
+            node_ids: sample.node_ids.iter().rev().cloned().collect(),
+            node_weights: sample.node_weights.iter().rev().copied().collect(),
+        };
+
+        assert_eq!(
+            query.sample_for_round(&sample, 7).unwrap(),
+            query.sample_for_round(&reversed, 7).unwrap()
+        );
+    }
+
+    #[test]
+    fn clamped_query_samples_every_node() {
+        let query: NodeQuery = NodeQuery::new(20, "0".into());
+        let sample = TestSample {
+            node_ids: (0..10).map(|i| i.to_string()).collect(),
+            node_weights: (1..11usize).map(|i| i as f64).collect(),
+        };
+
+        let query = query.clamped(&sample);
+        assert_eq!(query.query_size(), 9);
+        let ids: HashSet<_> = query.sample(&sample).unwrap().into_iter().collect();
+        assert_eq!(ids.len(), 9);
+        assert!(!ids.contains(query.node_id()));
+    }
+
+    #[test]
+    fn sample_errors() {
+        let sample = TestSample {
+            node_ids: (0..10).map(|i| i.to_string()).collect(),
+            node_weights: (1..11usize).map(|i| i as f64).collect(),
+        };
+        assert_eq!(
+            NodeQuery::new(10, "0".into()).sample(&sample),
+            Err(ClaroError::SampleTooLarge {
+                requested: 10,
+                available: 9
+            })
+        );
+
+        let missing_weight = TestSample {
+            node_ids: (0..10).map(|i| i.to_string()).collect(),
+            node_weights: (1..10usize).map(|i| i as f64).collect(),
+        };
+        assert_eq!(
+            NodeQuery::new(5, "0".into()).sample(&missing_weight),
+            Err(ClaroError::MissingWeight("9".into()))
+        );
+
+        let negative_weight = TestSample {
+            node_ids: (0..10).map(|i| i.to_string()).collect(),
+            node_weights: (0..10).map(|_| -1.0).collect(),
+        };
+        assert!(matches!(
+            NodeQuery::new(5, "0".into()).sample(&negative_weight),
+            Err(ClaroError::InvalidWeights(_))
+        ));
+    }
 }
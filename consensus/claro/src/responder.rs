@@ -0,0 +1,301 @@
+// std
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+// crates
+use tracing::debug;
+// internal
+use crate::claro::Vote;
+use crate::query::NodeId;
+use crate::solver::ConsensusSolver;
+use crate::tracing::CLARO_TARGET_TAG;
+
+/// Incoming vote query
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VoteRequest<Tx> {
+    /// Transaction the requester wants our vote on
+    pub tx: Tx,
+    /// Node asking for the vote, as reported by itself
+    pub requester: NodeId,
+}
+
+/// Answer to a [`VoteRequest`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum VoteAnswer<Tx> {
+    /// Current local vote for the transaction
+    Vote(Vote<Tx>),
+    /// No opinion on the transaction, either unknown or not formed yet
+    Abstain,
+    /// Requester went over its [`RateLimit`]
+    RateLimited,
+}
+
+/// What to do with queries for transactions without a local solver
+pub enum UnknownTxPolicy<Tx, S> {
+    /// Do not vote
+    Abstain,
+    /// Start tracking the transaction with the solver built by `new_solver` and answer with its
+    /// initial vote. Only the latest `max_adopted` adopted solvers are kept, older ones are
+    /// dropped as new transactions are adopted.
+    Adopt {
+        new_solver: Box<dyn FnMut(&Tx) -> S + Send>,
+        max_adopted: usize,
+    },
+}
+
+/// Max amount of queries answered per requester within a time window
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RateLimit {
+    pub max_requests: usize,
+    pub window: Duration,
+}
+
+/// Requester queries count for the current window
+struct RequesterWindow {
+    start: Instant,
+    requests: usize,
+}
+
+/// Responder side of the vote queries
+/// Owns the local solvers and answers incoming [`VoteRequest`]s from their current opinions, so a
+/// single node can both step its solvers through [`crate::VoteQuery`] and answer its peers.
+/// Requests are rate limited per peer `P`, the requester identity as seen by the transport
+/// (a socket address, a channel...) rather than the self reported [`VoteRequest::requester`].
+pub struct Responder<Tx, S, P = NodeId> {
+    solvers: HashMap<Tx, S>,
+    unknown_tx_policy: UnknownTxPolicy<Tx, S>,
+    /// Adopted transactions still tracked, oldest first
+    adopted: VecDeque<Tx>,
+    /// Unlimited if `None`
+    rate_limit: Option<RateLimit>,
+    requesters: HashMap<P, RequesterWindow>,
+    /// Last time expired requester windows were evicted
+    last_eviction: Option<Instant>,
+}
+
+impl<Tx, S, P> Responder<Tx, S, P>
+where
+    Tx: Clone + Eq + Hash + Debug,
+    S: ConsensusSolver<Tx>,
+    P: Clone + Eq + Hash + Debug,
+{
+    pub fn new(unknown_tx_policy: UnknownTxPolicy<Tx, S>, rate_limit: Option<RateLimit>) -> Self {
+        Self {
+            solvers: HashMap::new(),
+            unknown_tx_policy,
+            adopted: VecDeque::new(),
+            rate_limit,
+            requesters: HashMap::new(),
+            last_eviction: None,
+        }
+    }
+
+    /// Track `tx` with `solver`, replacing any previous solver for it
+    /// An adopted `tx` becomes locally tracked, it is not dropped to make room for adopted ones.
+    pub fn insert(&mut self, tx: Tx, solver: S) -> Option<S> {
+        self.adopted.retain(|adopted| adopted != &tx);
+        self.solvers.insert(tx, solver)
+    }
+
+    pub fn remove(&mut self, tx: &Tx) -> Option<S> {
+        self.adopted.retain(|adopted| adopted != tx);
+        self.solvers.remove(tx)
+    }
+
+    pub fn solver(&self, tx: &Tx) -> Option<&S> {
+        self.solvers.get(tx)
+    }
+
+    /// Solver for `tx`, to step it with the locally queried votes
+    pub fn solver_mut(&mut self, tx: &Tx) -> Option<&mut S> {
+        self.solvers.get_mut(tx)
+    }
+
+    /// Answer `request` received from `peer` with the current local opinion on its transaction
+    pub fn answer(&mut self, peer: &P, request: &VoteRequest<Tx>) -> VoteAnswer<Tx> {
+        self.answer_at(peer, request, Instant::now())
+    }
+
+    /// Same as [`Responder::answer`], rate limiting as if it was received at `now`
+    pub fn answer_at(
+        &mut self,
+        peer: &P,
+        request: &VoteRequest<Tx>,
+        now: Instant,
+    ) -> VoteAnswer<Tx> {
+        if !self.allow(peer, now) {
+            debug!(
+                target: CLARO_TARGET_TAG,
+                peer = ?peer,
+                requester = %request.requester,
+                "rate limited"
+            );
+            return VoteAnswer::RateLimited;
+        }
+        if !self.solvers.contains_key(&request.tx) {
+            match &mut self.unknown_tx_policy {
+                UnknownTxPolicy::Abstain => return VoteAnswer::Abstain,
+                UnknownTxPolicy::Adopt {
+                    new_solver,
+                    max_adopted,
+                } => {
+                    if *max_adopted == 0 {
+                        return VoteAnswer::Abstain;
+                    }
+                    debug!(target: CLARO_TARGET_TAG, tx = ?request.tx, "adopting unknown tx");
+                    while self.adopted.len() >= *max_adopted {
+                        if let Some(oldest) = self.adopted.pop_front() {
+                            self.solvers.remove(&oldest);
+                        }
+                    }
+                    let solver = new_solver(&request.tx);
+                    self.solvers.insert(request.tx.clone(), solver);
+                    self.adopted.push_back(request.tx.clone());
+                }
+            }
+        }
+        match self.solvers[&request.tx].vote() {
+            Some(vote) => VoteAnswer::Vote(vote),
+            None => VoteAnswer::Abstain,
+        }
+    }
+
+    /// Count a request from `peer`, `false` if it goes over the rate limit
+    fn allow(&mut self, peer: &P, now: Instant) -> bool {
+        let rate_limit = match self.rate_limit {
+            Some(rate_limit) => rate_limit,
+            None => return true,
+        };
+        // at most once per window, drop the windows of peers that went quiet
+        let evict = self
+            .last_eviction
+            .map(|last| now.saturating_duration_since(last) >= rate_limit.window)
+            .unwrap_or(true);
+        if evict {
+            self.requesters.retain(|_, window| {
+                now.saturating_duration_since(window.start) < rate_limit.window
+            });
+            self.last_eviction = Some(now);
+        }
+        let window = self
+            .requesters
+            .entry(peer.clone())
+            .or_insert(RequesterWindow {
+                start: now,
+                requests: 0,
+            });
+        if now.saturating_duration_since(window.start) >= rate_limit.window {
+            window.start = now;
+            window.requests = 0;
+        }
+        if window.requests >= rate_limit.max_requests {
+            return false;
+        }
+        window.requests += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::claro::{ClaroSolver, Opinion, Vote};
+    use crate::query::{NodeId, NodeQuery};
+    use crate::responder::{RateLimit, Responder, UnknownTxPolicy, VoteAnswer, VoteRequest};
+    use crate::testing::configuration;
+    use std::time::{Duration, Instant};
+
+    fn solver(opinion: Opinion<u32>) -> ClaroSolver<u32> {
+        let config = configuration();
+        ClaroSolver::with_initial_opinion(config, NodeQuery::new(10, "node_1".into()), opinion)
+            .unwrap()
+    }
+
+    fn request(tx: u32) -> VoteRequest<u32> {
+        VoteRequest {
+            tx,
+            requester: "node_2".into(),
+        }
+    }
+
+    fn peer(node_id: &str) -> NodeId {
+        node_id.into()
+    }
+
+    #[test]
+    fn answers_local_opinion() {
+        let mut responder = Responder::new(UnknownTxPolicy::Abstain, None);
+        responder.insert(1, solver(Opinion::No(1)));
+        responder.insert(2, solver(Opinion::None(2)));
+        let peer = peer("node_2");
+        assert_eq!(
+            responder.answer(&peer, &request(1)),
+            VoteAnswer::Vote(Vote::No(1))
+        );
+        assert_eq!(responder.answer(&peer, &request(2)), VoteAnswer::Abstain);
+        assert_eq!(responder.answer(&peer, &request(3)), VoteAnswer::Abstain);
+        assert!(responder.solver(&3).is_none());
+    }
+
+    #[test]
+    fn adopts_unknown_tx() {
+        let mut responder = Responder::new(
+            UnknownTxPolicy::Adopt {
+                new_solver: Box::new(|tx| solver(Opinion::Yes(*tx))),
+                max_adopted: 2,
+            },
+            None,
+        );
+        let peer = peer("node_2");
+        assert_eq!(
+            responder.answer(&peer, &request(3)),
+            VoteAnswer::Vote(Vote::Yes(3))
+        );
+        assert!(responder.solver(&3).is_some());
+        // the oldest adopted solver makes room for the new ones
+        responder.answer(&peer, &request(4));
+        responder.answer(&peer, &request(5));
+        assert!(responder.solver(&3).is_none());
+        // locally tracked transactions are never dropped
+        responder.insert(4, solver(Opinion::No(4)));
+        responder.answer(&peer, &request(6));
+        responder.answer(&peer, &request(7));
+        assert!(responder.solver(&4).is_some());
+        assert!(responder.solver(&5).is_none());
+        assert!(responder.solver(&6).is_some());
+        assert!(responder.solver(&7).is_some());
+    }
+
+    #[test]
+    fn rate_limits_peers() {
+        let mut responder = Responder::new(
+            UnknownTxPolicy::Abstain,
+            Some(RateLimit {
+                max_requests: 2,
+                window: Duration::from_secs(1),
+            }),
+        );
+        responder.insert(1, solver(Opinion::Yes(1)));
+        let now = Instant::now();
+        let answer = VoteAnswer::Vote(Vote::Yes(1));
+        let peer_2 = peer("node_2");
+        assert_eq!(responder.answer_at(&peer_2, &request(1), now), answer);
+        assert_eq!(responder.answer_at(&peer_2, &request(1), now), answer);
+        // the self reported requester does not reset the budget of its peer
+        let spoofed = VoteRequest {
+            tx: 1,
+            requester: "node_4".into(),
+        };
+        assert_eq!(
+            responder.answer_at(&peer_2, &spoofed, now),
+            VoteAnswer::RateLimited
+        );
+        // other peers keep their own budget
+        let peer_3 = peer("node_3");
+        assert_eq!(responder.answer_at(&peer_3, &request(1), now), answer);
+        // budget is restored once the window is over, expired windows are evicted
+        let later = now + Duration::from_secs(1);
+        assert_eq!(responder.answer_at(&peer_2, &request(1), later), answer);
+        assert_eq!(responder.requesters.len(), 1);
+    }
+}
@@ -0,0 +1,205 @@
+// std
+// crates
+// internal
+use crate::claro::ClaroConfiguration;
+use crate::error::ClaroError;
+use crate::multi::ClaroState;
+use crate::query::NodeWeight;
+
+/// Claro round computed evidence, confidence and alpha for a single conflict set value
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClaroRoundCalculation {
+    pub confidence: f32,
+    /// Evidence of the current round votes
+    pub e1: f32,
+    /// Evidence accumulated over past rounds
+    pub e2: f32,
+    /// Blended evidence, compared against `alpha`
+    pub e: f32,
+    pub alpha: f32,
+}
+
+/// How a Claro round folds votes into the state and blends evidence, confidence and alpha
+/// Every method defaults to the original Claro formula. Solvers use the
+/// [`ClaroConfiguration::formula`] preset unless built with their own computation, see
+/// [`crate::MultiClaroSolver::with_round_computation`].
+pub trait RoundComputation {
+    /// Add up the round votes into `state`
+    fn update<V: Clone + PartialEq>(&self, state: &mut ClaroState<V>, votes: &[(V, NodeWeight)]) {
+        state.update_evidence(votes);
+        state.update_confidence(votes);
+    }
+
+    /// Compute the round for `value`, `state` already holds the round votes
+    fn round<V: Clone + PartialEq>(
+        &self,
+        configuration: &ClaroConfiguration,
+        state: &ClaroState<V>,
+        votes: &[(V, NodeWeight)],
+        value: &V,
+    ) -> ClaroRoundCalculation {
+        let total_votes: NodeWeight = votes.iter().map(|(_, weight)| weight).sum();
+        let value_votes: NodeWeight = votes
+            .iter()
+            .filter(|(v, _)| v == value)
+            .map(|(_, weight)| weight)
+            .sum();
+        let confidence = state.confidence() as f32
+            / (state.confidence() as f32 + configuration.look_ahead as f32);
+
+        let e1 = value_votes as f32 / total_votes as f32;
+        let e2 = state.evidence_for(value) as f32 / state.evidence_accumulated() as f32;
+        let e = e1 * (1f32 - confidence) + e2 * confidence;
+        let alpha = configuration.evidence_alpha * (1f32 - confidence)
+            + configuration.evidence_alpha_2 * confidence;
+
+        ClaroRoundCalculation {
+            confidence,
+            e1,
+            e2,
+            e,
+            alpha,
+        }
+    }
+}
+
+/// Original Claro formula, evidence accumulates over every round
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinearRound;
+
+impl RoundComputation for LinearRound {}
+
+/// Accumulated evidence only covers the latest `window` rounds, confidence still covers all of them
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowedRound {
+    pub window: usize,
+}
+
+impl RoundComputation for WindowedRound {
+    fn update<V: Clone + PartialEq>(&self, state: &mut ClaroState<V>, votes: &[(V, NodeWeight)]) {
+        state.update_evidence(votes);
+        state.update_confidence(votes);
+        for expired in state.push_window(votes, self.window) {
+            state.retract_evidence(&expired);
+        }
+    }
+}
+
+/// Accumulated evidence fades by `decay` every round before adding up the round votes,
+/// confidence does not fade
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecayRound {
+    /// Evidence kept from one round to the next, within `(0.0, 1.0]`
+    pub decay: f32,
+}
+
+impl RoundComputation for DecayRound {
+    fn update<V: Clone + PartialEq>(&self, state: &mut ClaroState<V>, votes: &[(V, NodeWeight)]) {
+        state.scale_evidence(self.decay as NodeWeight);
+        state.update_evidence(votes);
+        state.update_confidence(votes);
+    }
+}
+
+/// Round computation presets
+/// Enum to avoid Boxing the computation within [`ClaroConfiguration`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RoundFormula {
+    #[default]
+    Linear,
+    Windowed(WindowedRound),
+    Decay(DecayRound),
+}
+
+impl RoundFormula {
+    pub fn validate(&self) -> Result<(), ClaroError> {
+        match self {
+            RoundFormula::Linear => Ok(()),
+            RoundFormula::Windowed(WindowedRound { window }) if *window == 0 => Err(
+                ClaroError::InvalidConfiguration("window must be at least 1".to_string()),
+            ),
+            RoundFormula::Windowed(_) => Ok(()),
+            RoundFormula::Decay(DecayRound { decay }) if !(*decay > 0f32 && *decay <= 1f32) => {
+                Err(ClaroError::InvalidConfiguration(format!(
+                    "decay must be within (0.0, 1.0], got {decay}"
+                )))
+            }
+            RoundFormula::Decay(_) => Ok(()),
+        }
+    }
+}
+
+impl RoundComputation for RoundFormula {
+    fn update<V: Clone + PartialEq>(&self, state: &mut ClaroState<V>, votes: &[(V, NodeWeight)]) {
+        match self {
+            RoundFormula::Linear => LinearRound.update(state, votes),
+            RoundFormula::Windowed(formula) => formula.update(state, votes),
+            RoundFormula::Decay(formula) => formula.update(state, votes),
+        }
+    }
+
+    fn round<V: Clone + PartialEq>(
+        &self,
+        configuration: &ClaroConfiguration,
+        state: &ClaroState<V>,
+        votes: &[(V, NodeWeight)],
+        value: &V,
+    ) -> ClaroRoundCalculation {
+        match self {
+            RoundFormula::Linear => LinearRound.round(configuration, state, votes, value),
+            RoundFormula::Windowed(formula) => formula.round(configuration, state, votes, value),
+            RoundFormula::Decay(formula) => formula.round(configuration, state, votes, value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::multi::ClaroState;
+    use crate::round::{DecayRound, RoundComputation, RoundFormula, WindowedRound};
+
+    #[test]
+    fn windowed_forgets_old_rounds() {
+        let formula = WindowedRound { window: 2 };
+        let mut state = ClaroState::with_values([true, false]);
+        formula.update(&mut state, &[(true, 1.0), (true, 1.0)]);
+        formula.update(&mut state, &[(false, 1.0)]);
+        formula.update(&mut state, &[(false, 1.0)]);
+        assert_eq!(state.evidence_for(&true), 0.0);
+        assert_eq!(state.evidence_for(&false), 2.0);
+        assert_eq!(state.evidence_accumulated(), 2.0);
+        assert_eq!(state.confidence(), 4.0);
+    }
+
+    #[test]
+    fn decay_fades_evidence() {
+        let formula = DecayRound { decay: 0.5 };
+        let mut state = ClaroState::with_values([true, false]);
+        formula.update(&mut state, &[(true, 1.0), (true, 1.0)]);
+        formula.update(&mut state, &[(false, 1.0)]);
+        assert_eq!(state.evidence_for(&true), 1.0);
+        assert_eq!(state.evidence_for(&false), 1.0);
+        assert_eq!(state.evidence_accumulated(), 2.0);
+        assert_eq!(state.confidence(), 3.0);
+    }
+
+    #[test]
+    fn validation() {
+        assert!(RoundFormula::default().validate().is_ok());
+        assert!(RoundFormula::Windowed(WindowedRound { window: 0 })
+            .validate()
+            .is_err());
+        assert!(RoundFormula::Decay(DecayRound { decay: 0.0 })
+            .validate()
+            .is_err());
+        assert!(RoundFormula::Decay(DecayRound { decay: 1.0 })
+            .validate()
+            .is_ok());
+    }
+}
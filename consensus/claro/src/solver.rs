@@ -0,0 +1,30 @@
+// std
+use std::error::Error;
+// crates
+// internal
+use crate::claro::{Decision, Opinion, Vote};
+use crate::query::{NodeQuery, QueryResponse};
+
+/// Common interface of the snow family consensus solvers
+/// Allows drivers, simulators and tests to work over any of the algorithms.
+pub trait ConsensusSolver<Tx> {
+    type Error: Error;
+
+    /// Compute a single round from the responses queried for `tx`
+    fn step(&mut self, tx: Tx, responses: &[QueryResponse<Tx>]) -> Result<(), Self::Error>;
+
+    fn decision(&self) -> Decision<Tx>;
+
+    fn opinion(&self) -> Opinion<Tx> {
+        match self.decision() {
+            Decision::Decided(opinion) | Decision::Undecided(opinion) => opinion,
+        }
+    }
+
+    /// Derive vote from it's current decision
+    fn vote(&self) -> Option<Vote<Tx>> {
+        self.decision().into()
+    }
+
+    fn node_query(&self) -> &NodeQuery;
+}
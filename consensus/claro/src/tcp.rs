@@ -0,0 +1,337 @@
+// std
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::io;
+use std::marker::PhantomData;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+// crates
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+// internal
+use crate::query::{
+    NodeId, NodeQuery, NodeWeight, NodesSample, QueryResponse, ResponseMetadata, ResponseVote,
+    VoteQuery,
+};
+use crate::responder::{Responder, VoteAnswer, VoteRequest};
+use crate::solver::ConsensusSolver;
+use crate::tracing::CLARO_TARGET_TAG;
+use crate::wire::{
+    decode_answer, decode_request, encode_answer, encode_request, read_frame, write_frame, WireTx,
+};
+
+/// Address and stake of a remote node
+#[derive(Debug, Clone, Copy)]
+pub struct TcpPeer {
+    pub address: SocketAddr,
+    pub weight: NodeWeight,
+}
+
+/// [`VoteQuery`] over TCP
+/// Samples the known peers through [`NodeQuery::clamped`], all of them once the query grows past
+/// their count, and asks each of them concurrently on a
+/// fresh connection. Responses carry the peer weight and the answer latency. Failing, abstaining
+/// or rate limited peers are reported as missing, those not answering within `peer_timeout` as
+/// timed out.
+pub struct TcpVoteQuery<Tx> {
+    peers: HashMap<NodeId, TcpPeer>,
+    peer_timeout: Duration,
+    _tx: PhantomData<Tx>,
+}
+
+impl<Tx> TcpVoteQuery<Tx> {
+    pub fn new(peers: HashMap<NodeId, TcpPeer>, peer_timeout: Duration) -> Self {
+        Self {
+            peers,
+            peer_timeout,
+            _tx: PhantomData,
+        }
+    }
+
+    /// Add or replace a known peer
+    pub fn insert_peer(&mut self, node_id: NodeId, peer: TcpPeer) {
+        self.peers.insert(node_id, peer);
+    }
+}
+
+impl<Tx> NodesSample for TcpVoteQuery<Tx> {
+    fn nodes(&self) -> Vec<NodeId> {
+        self.peers.keys().cloned().collect()
+    }
+
+    fn weights(&self) -> HashMap<&NodeId, NodeWeight> {
+        self.peers
+            .iter()
+            .map(|(node_id, peer)| (node_id, peer.weight))
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl<Tx> VoteQuery for TcpVoteQuery<Tx>
+where
+    Tx: WireTx + Send + Sync + 'static,
+{
+    type Tx = Tx;
+
+    async fn query(
+        &mut self,
+        node_query: &NodeQuery,
+        tx: Self::Tx,
+    ) -> Vec<QueryResponse<Self::Tx>> {
+        // all of the peers get asked once the query grows past their count
+        let sample = match node_query.clamped(self).sample(self) {
+            Ok(sample) => sample,
+            Err(e) => {
+                debug!(target: CLARO_TARGET_TAG, error = %e, "unable to sample peers");
+                return vec![];
+            }
+        };
+        let request = match encode_request(&VoteRequest {
+            tx,
+            requester: node_query.node_id().clone(),
+        }) {
+            Ok(request) => Arc::new(request),
+            Err(e) => {
+                debug!(target: CLARO_TARGET_TAG, error = %e, "unable to encode request");
+                return vec![];
+            }
+        };
+        let asks: Vec<_> = sample
+            .into_iter()
+            .filter_map(|node_id| {
+                let peer = *self.peers.get(&node_id)?;
+                let ask = tokio::spawn(ask::<Tx>(peer.address, request.clone(), self.peer_timeout));
+                Some((node_id, peer, ask))
+            })
+            .collect();
+        let mut responses = Vec::with_capacity(asks.len());
+        for (node_id, peer, ask) in asks {
+            let (answer, latency) = ask.await.expect("Peer requests do not panic");
+            let vote = match answer {
+                Ok(VoteAnswer::Vote(vote)) => ResponseVote::Vote(vote),
+                Ok(_) => ResponseVote::Missing,
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => ResponseVote::TimedOut,
+                Err(e) => {
+                    debug!(target: CLARO_TARGET_TAG, node_id = %node_id, error = %e, "peer failed");
+                    ResponseVote::Missing
+                }
+            };
+            let metadata = ResponseMetadata {
+                weight: Some(peer.weight),
+                latency: Some(latency),
+            };
+            responses.push(QueryResponse::new(node_id, vote).with_metadata(metadata));
+        }
+        responses
+    }
+}
+
+/// Send the encoded `request` to `address` and wait for its answer, along with the time it took
+async fn ask<Tx: WireTx>(
+    address: SocketAddr,
+    request: Arc<Vec<u8>>,
+    timeout: Duration,
+) -> (io::Result<VoteAnswer<Tx>>, Duration) {
+    let start = Instant::now();
+    let exchange = async {
+        let mut stream = TcpStream::connect(address).await?;
+        write_frame(&mut stream, &request).await?;
+        decode_answer(&read_frame(&mut stream).await?)
+    };
+    let answer = tokio::time::timeout(timeout, exchange)
+        .await
+        .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))
+        .and_then(|answer| answer);
+    (answer, start.elapsed())
+}
+
+/// TCP server answering vote requests from a shared [`Responder`]
+/// The same responder can be used locally to step its solvers, making the node both a querier
+/// and a responder. Requests are rate limited per remote IP address.
+pub struct TcpVoteServer<Tx, S> {
+    listener: TcpListener,
+    responder: Arc<Mutex<Responder<Tx, S, IpAddr>>>,
+}
+
+impl<Tx, S> TcpVoteServer<Tx, S>
+where
+    Tx: WireTx + Clone + Eq + Hash + Debug + Send + 'static,
+    S: ConsensusSolver<Tx> + Send + 'static,
+{
+    pub async fn bind(
+        address: impl ToSocketAddrs,
+        responder: Arc<Mutex<Responder<Tx, S, IpAddr>>>,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(address).await?,
+            responder,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accept and answer connections until `cancel` is triggered
+    pub async fn serve(self, cancel: CancellationToken) -> io::Result<()> {
+        loop {
+            let (stream, address) = tokio::select! {
+                _ = cancel.cancelled() => return Ok(()),
+                accepted = self.listener.accept() => accepted?,
+            };
+            let responder = self.responder.clone();
+            let cancel = cancel.clone();
+            tokio::spawn(async move {
+                tokio::select! {
+                    _ = cancel.cancelled() => {}
+                    result = answer_connection(stream, address.ip(), responder) => {
+                        if let Err(e) = result {
+                            debug!(target: CLARO_TARGET_TAG, address = %address, error = %e, "connection failed");
+                        }
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// Answer every request frame on `stream` until the peer closes it
+async fn answer_connection<Tx, S>(
+    mut stream: TcpStream,
+    peer: IpAddr,
+    responder: Arc<Mutex<Responder<Tx, S, IpAddr>>>,
+) -> io::Result<()>
+where
+    Tx: WireTx + Clone + Eq + Hash + Debug,
+    S: ConsensusSolver<Tx>,
+{
+    loop {
+        let payload = match read_frame(&mut stream).await {
+            Ok(payload) => payload,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let request = decode_request(&payload)?;
+        let answer = responder.lock().await.answer(&peer, &request);
+        write_frame(&mut stream, &encode_answer(&answer)).await?;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::claro::{ClaroConfiguration, ClaroSolver, Decision, Opinion, QueryConfiguration};
+    use crate::query::{NodeId, NodeQuery, ResponseVote, VoteQuery};
+    use crate::responder::{Responder, UnknownTxPolicy};
+    use crate::tcp::{TcpPeer, TcpVoteQuery, TcpVoteServer};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio::sync::Mutex;
+    use tokio_util::sync::CancellationToken;
+
+    fn configuration() -> ClaroConfiguration {
+        ClaroConfiguration {
+            query: QueryConfiguration::new(4),
+            ..crate::testing::configuration()
+        }
+    }
+
+    fn solver(node_id: &str, opinion: Opinion<u64>) -> ClaroSolver<u64> {
+        let node_query = NodeQuery::new(4, node_id.into());
+        ClaroSolver::with_initial_opinion(configuration(), node_query, opinion).unwrap()
+    }
+
+    /// Serve a solver per opinion over loopback, peers are named `1..`
+    async fn serve_peers(
+        opinions: &[Opinion<u64>],
+        cancel: &CancellationToken,
+    ) -> HashMap<NodeId, TcpPeer> {
+        let mut peers = HashMap::new();
+        for (i, &opinion) in opinions.iter().enumerate() {
+            let node_id = (i + 1).to_string();
+            let mut responder = Responder::new(UnknownTxPolicy::Abstain, None);
+            responder.insert(7, solver(&node_id, opinion));
+            let server = TcpVoteServer::bind("127.0.0.1:0", Arc::new(Mutex::new(responder)))
+                .await
+                .unwrap();
+            let address = server.local_addr().unwrap();
+            peers.insert(
+                node_id,
+                TcpPeer {
+                    address,
+                    weight: 1.0,
+                },
+            );
+            tokio::spawn(server.serve(cancel.clone()));
+        }
+        peers
+    }
+
+    #[tokio::test]
+    async fn decides_over_loopback() {
+        let cancel = CancellationToken::new();
+        let peers = serve_peers(&[Opinion::Yes(7); 5], &cancel).await;
+        let mut query = TcpVoteQuery::new(peers, Duration::from_secs(1));
+        let mut solver = solver("0", Opinion::None(7));
+        while matches!(solver.decision(), Decision::Undecided(_)) {
+            let responses = query.query(solver.node_query(), 7).await;
+            assert_eq!(responses.len(), 4);
+            assert!(responses
+                .iter()
+                .all(|response| response.as_vote().is_some()));
+            solver.step(7, &responses).unwrap();
+        }
+        assert_eq!(solver.decision(), Decision::Decided(Opinion::Yes(7)));
+        cancel.cancel();
+    }
+
+    #[tokio::test]
+    async fn query_grown_past_peers_asks_all_of_them() {
+        let cancel = CancellationToken::new();
+        let opinions = [
+            Opinion::Yes(7),
+            Opinion::Yes(7),
+            Opinion::No(7),
+            Opinion::No(7),
+        ];
+        let peers = serve_peers(&opinions, &cancel).await;
+        let mut query = TcpVoteQuery::new(peers, Duration::from_secs(1));
+        let mut solver = solver("0", Opinion::None(7));
+        // an even split never goes over alpha, growing the query past the 4 known peers
+        for _ in 0..3 {
+            let responses = query.query(solver.node_query(), 7).await;
+            assert_eq!(responses.len(), 4);
+            assert!(responses
+                .iter()
+                .all(|response| response.as_vote().is_some()));
+            solver.step(7, &responses).unwrap();
+        }
+        assert!(solver.node_query().query_size() > 4);
+        cancel.cancel();
+    }
+
+    #[tokio::test]
+    async fn silent_peers_time_out() {
+        // accepts connections but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let peers = HashMap::from([(
+            "1".to_string(),
+            TcpPeer {
+                address: listener.local_addr().unwrap(),
+                weight: 1.0,
+            },
+        )]);
+        let mut query = TcpVoteQuery::<u64>::new(peers, Duration::from_millis(20));
+        let responses = query.query(&NodeQuery::new(1, "0".into()), 7).await;
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].node_id, "1");
+        assert_eq!(responses[0].vote, ResponseVote::TimedOut);
+        assert_eq!(responses[0].metadata.weight, Some(1.0));
+    }
+}
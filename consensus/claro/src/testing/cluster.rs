@@ -0,0 +1,435 @@
+// std
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::Duration;
+// crates
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tracing::debug;
+// internal
+use crate::claro::{ClaroSolver, Decision};
+use crate::query::{
+    NodeId, NodeQuery, NodeWeight, NodesSample, QueryResponse, ResponseVote, VoteQuery,
+};
+use crate::responder::{RateLimit, Responder, UnknownTxPolicy, VoteAnswer, VoteRequest};
+use crate::solver::ConsensusSolver;
+use crate::tracing::CLARO_TARGET_TAG;
+
+/// Vote request in flight, answered through `reply`
+pub struct ClusterRequest<Tx> {
+    pub request: VoteRequest<Tx>,
+    pub reply: oneshot::Sender<VoteAnswer<Tx>>,
+}
+
+/// Network and responder faults injected by the cluster
+pub trait ClusterHook<Tx>: Send + Sync {
+    /// Delay before the answer of `responder` reaches `requester`, `None` drops it
+    fn deliver(&self, _requester: &NodeId, _responder: &NodeId) -> Option<Duration> {
+        Some(Duration::ZERO)
+    }
+
+    /// Rewrite the answer of `responder`, used to model byzantine nodes
+    fn answer(&self, _responder: &NodeId, answer: VoteAnswer<Tx>) -> VoteAnswer<Tx> {
+        answer
+    }
+}
+
+/// Reliable network, every node answers honestly
+pub struct NoFaults;
+
+impl<Tx> ClusterHook<Tx> for NoFaults {}
+
+/// Seeded random drops and delays plus fixed byzantine answers
+pub struct Faults<Tx> {
+    drop_rate: f64,
+    delay: (Duration, Duration),
+    byzantine: HashMap<NodeId, VoteAnswer<Tx>>,
+    rng: std::sync::Mutex<ChaCha8Rng>,
+}
+
+impl<Tx> Faults<Tx> {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            drop_rate: 0.0,
+            delay: (Duration::ZERO, Duration::ZERO),
+            byzantine: HashMap::new(),
+            rng: std::sync::Mutex::new(ChaCha8Rng::seed_from_u64(seed)),
+        }
+    }
+
+    /// Drop each answer with `drop_rate` probability
+    /// Panics if `drop_rate` is not within `[0.0, 1.0]`.
+    pub fn with_drop_rate(mut self, drop_rate: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&drop_rate),
+            "drop_rate must be within [0.0, 1.0], got {drop_rate}"
+        );
+        self.drop_rate = drop_rate;
+        self
+    }
+
+    /// Delay each answer uniformly within `min..=max`
+    /// Panics if `min` is over `max`.
+    pub fn with_delay(mut self, min: Duration, max: Duration) -> Self {
+        assert!(min <= max, "delay min {min:?} must not exceed max {max:?}");
+        self.delay = (min, max);
+        self
+    }
+
+    /// Make `node_id` always answer with `answer`, whatever its solver opinion
+    pub fn with_byzantine(mut self, node_id: NodeId, answer: VoteAnswer<Tx>) -> Self {
+        self.byzantine.insert(node_id, answer);
+        self
+    }
+}
+
+impl<Tx: Clone + Send + Sync> ClusterHook<Tx> for Faults<Tx> {
+    fn deliver(&self, _requester: &NodeId, _responder: &NodeId) -> Option<Duration> {
+        let mut rng = self.rng.lock().expect("Faults rng lock is never poisoned");
+        if rng.gen_bool(self.drop_rate) {
+            return None;
+        }
+        let (min, max) = self.delay;
+        Some(rng.gen_range(min..=max))
+    }
+
+    fn answer(&self, responder: &NodeId, answer: VoteAnswer<Tx>) -> VoteAnswer<Tx> {
+        self.byzantine.get(responder).cloned().unwrap_or(answer)
+    }
+}
+
+/// Cluster wide configuration
+#[derive(Debug, Clone, Copy)]
+pub struct ClusterConfiguration {
+    /// Max amount of rounds each node runs before giving up
+    pub max_rounds: usize,
+    /// Max time a node waits for the answers of a single query
+    pub query_timeout: Duration,
+    /// Rate limit applied by every responder, unlimited if `None`
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl Default for ClusterConfiguration {
+    fn default() -> Self {
+        Self {
+            max_rounds: 1000,
+            query_timeout: Duration::from_millis(100),
+            rate_limit: None,
+        }
+    }
+}
+
+/// Uniform sample over the cluster nodes
+struct Peers(Vec<NodeId>);
+
+impl NodesSample for Peers {
+    fn nodes(&self) -> Vec<NodeId> {
+        self.0.clone()
+    }
+
+    fn weights(&self) -> HashMap<&NodeId, NodeWeight> {
+        self.0.iter().map(|node_id| (node_id, 1.0)).collect()
+    }
+}
+
+/// [`VoteQuery`] sending [`ClusterRequest`]s to the other cluster nodes over channels
+/// Dropped, abstained or rate limited answers are reported as missing, late ones as timed out.
+pub struct ChannelQuery<Tx> {
+    node_id: NodeId,
+    peers: Peers,
+    senders: Arc<HashMap<NodeId, mpsc::Sender<ClusterRequest<Tx>>>>,
+    timeout: Duration,
+    round: u64,
+}
+
+#[async_trait::async_trait]
+impl<Tx: Clone + Send + Sync> VoteQuery for ChannelQuery<Tx> {
+    type Tx = Tx;
+
+    async fn query(
+        &mut self,
+        node_query: &NodeQuery,
+        tx: Self::Tx,
+    ) -> Vec<QueryResponse<Self::Tx>> {
+        // never ask for more nodes than the cluster has
+        let sample = node_query
+            .clamped(&self.peers)
+            .sample_for_round(&self.peers, self.round)
+            .expect("Cluster sample is clamped to the cluster size");
+        self.round += 1;
+        let mut replies = Vec::with_capacity(sample.len());
+        for peer in sample {
+            let (reply, receiver) = oneshot::channel();
+            let request = ClusterRequest {
+                request: VoteRequest {
+                    tx: tx.clone(),
+                    requester: self.node_id.clone(),
+                },
+                reply,
+            };
+            // a closed channel leaves the receiver without answer
+            let _ = self.senders[&peer].send(request).await;
+            replies.push((peer, receiver));
+        }
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        let mut responses = Vec::with_capacity(replies.len());
+        for (peer, receiver) in replies {
+            let vote = match tokio::time::timeout_at(deadline, receiver).await {
+                Ok(Ok(VoteAnswer::Vote(vote))) => ResponseVote::Vote(vote),
+                Ok(_) => ResponseVote::Missing,
+                Err(_) => ResponseVote::TimedOut,
+            };
+            responses.push(QueryResponse::new(peer, vote));
+        }
+        responses
+    }
+}
+
+/// Final state of a single cluster node
+#[derive(Debug, Clone)]
+pub struct ClusterNodeOutcome<Tx, E> {
+    pub node_id: NodeId,
+    /// Amount of rounds the node ran
+    pub rounds: usize,
+    /// Decision when the node stopped, or the error its solver failed with
+    pub result: Result<Decision<Tx>, E>,
+}
+
+/// In process cluster of consensus nodes, each running as a tokio task
+/// Nodes query each other through [`ChannelQuery`] and answer from a [`Responder`] over their
+/// own solver, with the faults injected by a [`ClusterHook`].
+pub struct Cluster<Tx, S = ClaroSolver<Tx>> {
+    configuration: ClusterConfiguration,
+    solvers: Vec<(NodeId, S)>,
+    hook: Arc<dyn ClusterHook<Tx>>,
+}
+
+impl<Tx, S> Cluster<Tx, S>
+where
+    Tx: Clone + Eq + Hash + Debug + Send + Sync + 'static,
+    S: ConsensusSolver<Tx> + Send + 'static,
+    S::Error: Send,
+{
+    pub fn new(
+        configuration: ClusterConfiguration,
+        solvers: Vec<(NodeId, S)>,
+        hook: impl ClusterHook<Tx> + 'static,
+    ) -> Self {
+        Self {
+            configuration,
+            solvers,
+            hook: Arc::new(hook),
+        }
+    }
+
+    /// Run every node on `tx` until all of them are decided or out of rounds
+    /// Outcomes follow the order the solvers were given in.
+    pub async fn run(self, tx: Tx) -> Vec<ClusterNodeOutcome<Tx, S::Error>> {
+        let Self {
+            configuration,
+            solvers,
+            hook,
+        } = self;
+        let peers: Vec<NodeId> = solvers.iter().map(|(node_id, _)| node_id.clone()).collect();
+        let mut senders = HashMap::new();
+        let mut responders = Vec::new();
+        for (node_id, solver) in solvers {
+            let (sender, receiver) = mpsc::channel(peers.len().max(1) * 4);
+            senders.insert(node_id.clone(), sender);
+            let mut responder = Responder::new(UnknownTxPolicy::Abstain, configuration.rate_limit);
+            responder.insert(tx.clone(), solver);
+            let responder = Arc::new(Mutex::new(responder));
+            tokio::spawn(serve(
+                node_id.clone(),
+                responder.clone(),
+                receiver,
+                hook.clone(),
+            ));
+            responders.push((node_id, responder));
+        }
+        let senders = Arc::new(senders);
+        let nodes: Vec<_> = responders
+            .into_iter()
+            .map(|(node_id, responder)| {
+                let query = ChannelQuery {
+                    node_id: node_id.clone(),
+                    peers: Peers(peers.clone()),
+                    senders: senders.clone(),
+                    timeout: configuration.query_timeout,
+                    round: 0,
+                };
+                tokio::spawn(run_node(
+                    node_id,
+                    responder,
+                    query,
+                    tx.clone(),
+                    configuration.max_rounds,
+                ))
+            })
+            .collect();
+        // responders stop once every query holding their sender is gone
+        drop(senders);
+        let mut outcomes = Vec::with_capacity(nodes.len());
+        for node in nodes {
+            outcomes.push(node.await.expect("Cluster nodes do not panic"));
+        }
+        outcomes
+    }
+}
+
+/// Answer incoming requests until every sender is dropped
+async fn serve<Tx, S>(
+    node_id: NodeId,
+    responder: Arc<Mutex<Responder<Tx, S>>>,
+    mut requests: mpsc::Receiver<ClusterRequest<Tx>>,
+    hook: Arc<dyn ClusterHook<Tx>>,
+) where
+    Tx: Clone + Eq + Hash + Debug + Send + 'static,
+    S: ConsensusSolver<Tx>,
+{
+    while let Some(ClusterRequest { request, reply }) = requests.recv().await {
+        // in process channels are trusted to carry the actual requester
+        let answer = responder.lock().await.answer(&request.requester, &request);
+        let answer = hook.answer(&node_id, answer);
+        match hook.deliver(&request.requester, &node_id) {
+            None => {
+                debug!(target: CLARO_TARGET_TAG, requester = %request.requester, responder = %node_id, "dropped")
+            }
+            Some(delay) if delay.is_zero() => {
+                let _ = reply.send(answer);
+            }
+            Some(delay) => {
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    let _ = reply.send(answer);
+                });
+            }
+        }
+    }
+}
+
+/// Query and step the node solver until it is decided or out of rounds
+async fn run_node<Tx, S>(
+    node_id: NodeId,
+    responder: Arc<Mutex<Responder<Tx, S>>>,
+    mut query: ChannelQuery<Tx>,
+    tx: Tx,
+    max_rounds: usize,
+) -> ClusterNodeOutcome<Tx, S::Error>
+where
+    Tx: Clone + Eq + Hash + Debug + Send + Sync,
+    S: ConsensusSolver<Tx>,
+{
+    let mut rounds = 0;
+    let result = loop {
+        let node_query = {
+            let responder = responder.lock().await;
+            let solver = responder.solver(&tx).expect("Node solver is never removed");
+            if rounds >= max_rounds || matches!(solver.decision(), Decision::Decided(_)) {
+                break Ok(solver.decision());
+            }
+            solver.node_query().clone()
+        };
+        let responses = query.query(&node_query, tx.clone()).await;
+        let mut responder = responder.lock().await;
+        let solver = responder
+            .solver_mut(&tx)
+            .expect("Node solver is never removed");
+        if let Err(e) = solver.step(tx.clone(), &responses) {
+            break Err(e);
+        }
+        rounds += 1;
+    };
+    debug!(target: CLARO_TARGET_TAG, node_id = %node_id, rounds, "node stopped");
+    ClusterNodeOutcome {
+        node_id,
+        rounds,
+        result,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::claro::{ClaroConfiguration, ClaroSolver, Decision, Opinion, QueryConfiguration};
+    use crate::query::NodeQuery;
+    use crate::responder::VoteAnswer;
+    use crate::testing::cluster::{Cluster, ClusterConfiguration, Faults, NoFaults};
+    use crate::testing::configuration;
+    use crate::Vote;
+    use std::time::Duration;
+
+    fn solvers(honest: usize, opinion: Opinion<bool>) -> Vec<(String, ClaroSolver<bool>)> {
+        let config = ClaroConfiguration {
+            query: QueryConfiguration::new(5),
+            ..configuration()
+        };
+        (0..honest)
+            .map(|i| {
+                let node_query = NodeQuery::new(5, i.to_string());
+                let solver =
+                    ClaroSolver::with_initial_opinion(config, node_query, opinion).unwrap();
+                (i.to_string(), solver)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn honest_cluster_decides() {
+        let cluster = Cluster::new(
+            ClusterConfiguration::default(),
+            solvers(10, Opinion::Yes(true)),
+            NoFaults,
+        );
+        let outcomes = cluster.run(true).await;
+        assert_eq!(outcomes.len(), 10);
+        for outcome in outcomes {
+            assert_eq!(outcome.result, Ok(Decision::Decided(Opinion::Yes(true))));
+        }
+    }
+
+    #[tokio::test]
+    async fn tolerates_faults() {
+        let faults = Faults::new(18042022)
+            .with_drop_rate(0.1)
+            .with_delay(Duration::ZERO, Duration::from_millis(2))
+            .with_byzantine("0".into(), VoteAnswer::Vote(Vote::No(true)))
+            .with_byzantine("1".into(), VoteAnswer::Abstain);
+        let cluster = Cluster::new(
+            ClusterConfiguration::default(),
+            solvers(12, Opinion::Yes(true)),
+            faults,
+        );
+        for outcome in cluster.run(true).await.into_iter().skip(2) {
+            assert_eq!(outcome.result, Ok(Decision::Decided(Opinion::Yes(true))));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "drop_rate must be within [0.0, 1.0]")]
+    fn drop_rate_out_of_range_panics() {
+        let _ = Faults::<bool>::new(0).with_drop_rate(1.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "must not exceed max")]
+    fn inverted_delay_panics() {
+        let _ = Faults::<bool>::new(0).with_delay(Duration::from_millis(2), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn out_of_rounds_when_unreachable() {
+        let configuration = ClusterConfiguration {
+            max_rounds: 3,
+            ..Default::default()
+        };
+        let faults = Faults::new(0).with_drop_rate(1.0);
+        let cluster = Cluster::new(configuration, solvers(6, Opinion::None(true)), faults);
+        for outcome in cluster.run(true).await {
+            assert_eq!(outcome.rounds, 3);
+            assert!(matches!(outcome.result, Ok(Decision::Undecided(_))));
+        }
+    }
+}
@@ -1 +1,22 @@
+// std
+// crates
+// internal
+use crate::claro::{BootstrapPolicy, ClaroConfiguration, EvidenceWeighting, QueryConfiguration};
+use crate::round::RoundFormula;
+
+pub mod cluster;
 pub mod query;
+
+/// Claro configuration shared by tests, override the fields under test only
+pub fn configuration() -> ClaroConfiguration {
+    ClaroConfiguration {
+        evidence_alpha: 0.8,
+        evidence_alpha_2: 0.5,
+        confidence_beta: 0.8,
+        look_ahead: 20,
+        weighting: EvidenceWeighting::Count,
+        formula: RoundFormula::Linear,
+        bootstrap: BootstrapPolicy::FirstVote,
+        query: QueryConfiguration::new(10),
+    }
+}
@@ -1,6 +1,15 @@
-use crate::{NodeQuery, Vote, VoteQuery};
+use crate::{NodeQuery, QueryResponse, ResponseVote, Vote, VoteQuery};
 use std::marker::PhantomData;
 
+/// Responses for `votes`, the responder of each vote named after its position
+pub fn responses<Tx: Clone>(votes: &[Vote<Tx>]) -> Vec<QueryResponse<Tx>> {
+    votes
+        .iter()
+        .enumerate()
+        .map(|(i, vote)| QueryResponse::new(i.to_string(), ResponseVote::Vote(vote.clone())))
+        .collect()
+}
+
 pub struct NoQuery<Tx>(PhantomData<Tx>);
 
 impl<Tx> Default for NoQuery<Tx> {
@@ -13,7 +22,11 @@ impl<Tx> Default for NoQuery<Tx> {
 impl<Tx: Send + Sync> VoteQuery for NoQuery<Tx> {
     type Tx = Tx;
 
-    async fn query(&mut self, _node_query: &NodeQuery, _tx: Self::Tx) -> Vec<Vote<Self::Tx>> {
+    async fn query(
+        &mut self,
+        _node_query: &NodeQuery,
+        _tx: Self::Tx,
+    ) -> Vec<QueryResponse<Self::Tx>> {
         vec![]
     }
 }
@@ -30,9 +43,11 @@ impl<Tx: Clone + Send + Sync> FixedQuery<Tx> {
 impl<Tx: Clone + Send + Sync> VoteQuery for FixedQuery<Tx> {
     type Tx = Tx;
 
-    async fn query(&mut self, node_query: &NodeQuery, _tx: Self::Tx) -> Vec<Vote<Self::Tx>> {
-        (0..node_query.query_size())
-            .map(|_| self.0.clone())
-            .collect()
+    async fn query(
+        &mut self,
+        node_query: &NodeQuery,
+        _tx: Self::Tx,
+    ) -> Vec<QueryResponse<Self::Tx>> {
+        responses(&vec![self.0.clone(); node_query.query_size()])
     }
 }
@@ -1,9 +1,24 @@
-use tracing_core::Subscriber;
+// std
+use std::fmt::{Debug, Write as _};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+// crates
+use tracing_core::field::{Field, Visit};
+use tracing_core::span::{Attributes, Id, Record};
+use tracing_core::{Event, Subscriber};
 use tracing_subscriber::filter::filter_fn;
 use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
 use tracing_subscriber::Layer;
+// internal
 
 pub const CLARO_TARGET_TAG: &str = "CLARO_TARGET";
+/// Name of the span every solver runs its rounds within
+pub const CLARO_SOLVER_SPAN: &str = "claro_solver";
+/// Name of the span of a single solver round, child of a [`CLARO_SOLVER_SPAN`] span
+pub const CLARO_ROUND_SPAN: &str = "claro_round";
 
 pub fn claro_tracing_layer_with_writer<W, S>(writer: W, filter_tag: &'static str) -> impl Layer<S>
 where
@@ -15,3 +30,464 @@ where
         .json()
         .with_filter(filter_fn(move |metadata| metadata.target() == filter_tag))
 }
+
+/// Aggregated figures over every closed [`CLARO_ROUND_SPAN`] span
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClaroMetrics {
+    /// Rounds computed
+    pub rounds: u64,
+    /// Rounds in which no value went over alpha, growing the query
+    pub query_growths: u64,
+    /// Rounds every decided solver took to decide, in decision order
+    pub rounds_to_decision: Vec<u64>,
+}
+
+impl ClaroMetrics {
+    pub fn mean_rounds_to_decision(&self) -> Option<f64> {
+        if self.rounds_to_decision.is_empty() {
+            return None;
+        }
+        let total: u64 = self.rounds_to_decision.iter().sum();
+        Some(total as f64 / self.rounds_to_decision.len() as f64)
+    }
+}
+
+/// Round span fields the metrics are built from
+#[derive(Default)]
+struct RoundRecord {
+    round: u64,
+    query_grew: bool,
+    decided: bool,
+}
+
+impl Visit for RoundRecord {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "round" {
+            self.round = value;
+        }
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        match field.name() {
+            "query_grew" => self.query_grew = value,
+            "decided" => self.decided = value,
+            _ => {}
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn Debug) {}
+}
+
+fn is_round_span(metadata: &tracing_core::Metadata<'_>) -> bool {
+    metadata.target() == CLARO_TARGET_TAG && metadata.name() == CLARO_ROUND_SPAN
+}
+
+/// Layer aggregating [`ClaroMetrics`] out of the solvers round spans
+/// Clones share the same metrics, keep one around to read them once installed.
+#[derive(Debug, Clone, Default)]
+pub struct ClaroMetricsLayer {
+    metrics: Arc<Mutex<ClaroMetrics>>,
+}
+
+impl ClaroMetricsLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copy of the metrics aggregated so far
+    pub fn metrics(&self) -> ClaroMetrics {
+        self.metrics.lock().unwrap().clone()
+    }
+}
+
+impl<S> Layer<S> for ClaroMetricsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if !is_round_span(attrs.metadata()) {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            let mut record = RoundRecord::default();
+            attrs.record(&mut record);
+            span.extensions_mut().insert(record);
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(record) = span.extensions_mut().get_mut::<RoundRecord>() {
+                values.record(record);
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let record = match ctx.span(&id) {
+            Some(span) => span.extensions_mut().remove::<RoundRecord>(),
+            None => None,
+        };
+        if let Some(record) = record {
+            let mut metrics = self.metrics.lock().unwrap();
+            metrics.rounds += 1;
+            if record.query_grew {
+                metrics.query_growths += 1;
+            }
+            if record.decided {
+                metrics.rounds_to_decision.push(record.round + 1);
+            }
+        }
+    }
+}
+
+/// Span or event field value, as written to the trace
+enum FieldValue {
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Str(String),
+}
+
+/// Every field of a span or event, in recording order
+#[derive(Default)]
+struct Fields(Vec<(&'static str, FieldValue)>);
+
+impl Fields {
+    fn set(&mut self, field: &Field, value: FieldValue) {
+        match self.0.iter_mut().find(|(name, _)| *name == field.name()) {
+            Some((_, old)) => *old = value,
+            None => self.0.push((field.name(), value)),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&FieldValue> {
+        self.0
+            .iter()
+            .find_map(|(field, value)| (*field == name).then_some(value))
+    }
+
+    fn write_json(&self, out: &mut String) {
+        out.push('{');
+        for (i, (name, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write_json_str(out, name);
+            out.push(':');
+            match value {
+                FieldValue::Bool(value) => write!(out, "{value}").unwrap(),
+                FieldValue::I64(value) => write!(out, "{value}").unwrap(),
+                FieldValue::U64(value) => write!(out, "{value}").unwrap(),
+                FieldValue::F64(value) if value.is_finite() => write!(out, "{value}").unwrap(),
+                FieldValue::F64(value) => write_json_str(out, &value.to_string()),
+                FieldValue::Str(value) => write_json_str(out, value),
+            }
+        }
+        out.push('}');
+    }
+}
+
+impl Visit for Fields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.set(field, FieldValue::F64(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set(field, FieldValue::I64(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set(field, FieldValue::U64(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set(field, FieldValue::Bool(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field, FieldValue::Str(value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.set(field, FieldValue::Str(format!("{value:?}")));
+    }
+}
+
+fn write_json_str(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Chrome trace span bookkeeping
+struct ChromeSpan {
+    start: Instant,
+    fields: Fields,
+}
+
+/// Trace events sink, writes the JSON array format one event per line
+struct ChromeTraceWriter<W: Write> {
+    writer: W,
+    events: usize,
+}
+
+impl<W: Write> ChromeTraceWriter<W> {
+    fn write_event(&mut self, event: &str) {
+        let separator = if self.events == 0 { "[\n" } else { ",\n" };
+        self.events += 1;
+        // tracing layers have no way to report failures, a broken writer only loses the trace
+        let _ = write!(self.writer, "{separator}{event}");
+    }
+
+    fn finish(&mut self) {
+        let end = if self.events == 0 { "[]\n" } else { "\n]\n" };
+        let _ = self.writer.write_all(end.as_bytes());
+        let _ = self.writer.flush();
+    }
+}
+
+/// Closes the trace written by a [`ChromeTraceLayer`] once dropped
+pub struct ChromeTraceGuard<W: Write> {
+    writer: Arc<Mutex<ChromeTraceWriter<W>>>,
+}
+
+impl<W: Write> Drop for ChromeTraceGuard<W> {
+    fn drop(&mut self) {
+        if let Ok(mut writer) = self.writer.lock() {
+            writer.finish();
+        }
+    }
+}
+
+/// Layer exporting spans and events in the Chrome trace event format
+/// Spans become complete events and events become instant events. Every root span, usually a
+/// [`CLARO_SOLVER_SPAN`] one, gets its own thread so each node timeline shows up on its own track
+/// when loaded in a trace viewer such as `chrome://tracing` or Perfetto.
+pub struct ChromeTraceLayer<W: Write> {
+    writer: Arc<Mutex<ChromeTraceWriter<W>>>,
+    start: Instant,
+}
+
+impl<W: Write + Send + 'static> ChromeTraceLayer<W> {
+    /// Layer writing to `writer`, the trace is complete once the returned guard is dropped
+    pub fn new(writer: W) -> (Self, ChromeTraceGuard<W>) {
+        let writer = Arc::new(Mutex::new(ChromeTraceWriter { writer, events: 0 }));
+        let guard = ChromeTraceGuard {
+            writer: Arc::clone(&writer),
+        };
+        let layer = Self {
+            writer,
+            start: Instant::now(),
+        };
+        (layer, guard)
+    }
+
+    fn timestamp(&self, instant: Instant) -> f64 {
+        instant.saturating_duration_since(self.start).as_secs_f64() * 1e6
+    }
+
+    fn write(&self, event: &str) {
+        self.writer.lock().unwrap().write_event(event);
+    }
+}
+
+/// Thread id of the span timeline, the root span id
+fn thread_id<S>(id: &Id, ctx: &Context<'_, S>) -> u64
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    ctx.span(id)
+        .and_then(|span| span.scope().from_root().next())
+        .map(|root| root.id().into_u64())
+        .unwrap_or_default()
+}
+
+impl<S, W> Layer<S> for ChromeTraceLayer<W>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: Write + Send + 'static,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let span = match ctx.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        if span.parent().is_none() {
+            let mut name = span.name().to_string();
+            if let Some(FieldValue::Str(node_id)) = fields.get("node_id") {
+                write!(name, " {node_id}").unwrap();
+            }
+            let mut event = String::from(r#"{"name":"thread_name","ph":"M","pid":1,"tid":"#);
+            write!(event, r#"{},"args":{{"name":"#, id.into_u64()).unwrap();
+            write_json_str(&mut event, &name);
+            event.push_str("}}");
+            self.write(&event);
+        }
+        span.extensions_mut().insert(ChromeSpan {
+            start: Instant::now(),
+            fields,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(chrome_span) = span.extensions_mut().get_mut::<ChromeSpan>() {
+                values.record(&mut chrome_span.fields);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let tid = ctx
+            .event_span(event)
+            .map(|span| thread_id(&span.id(), &ctx))
+            .unwrap_or_default();
+        let mut out = String::from(r#"{"name":"#);
+        match fields.get("message") {
+            Some(FieldValue::Str(message)) => write_json_str(&mut out, message),
+            _ => write_json_str(&mut out, event.metadata().name()),
+        }
+        out.push_str(r#","cat":"#);
+        write_json_str(&mut out, event.metadata().target());
+        write!(
+            out,
+            r#","ph":"i","s":"t","ts":{:.3},"pid":1,"tid":{tid},"args":"#,
+            self.timestamp(Instant::now())
+        )
+        .unwrap();
+        fields.write_json(&mut out);
+        out.push('}');
+        self.write(&out);
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let span = match ctx.span(&id) {
+            Some(span) => span,
+            None => return,
+        };
+        let chrome_span = match span.extensions_mut().remove::<ChromeSpan>() {
+            Some(chrome_span) => chrome_span,
+            None => return,
+        };
+        let end = Instant::now();
+        let mut out = String::from(r#"{"name":"#);
+        write_json_str(&mut out, span.name());
+        out.push_str(r#","cat":"#);
+        write_json_str(&mut out, span.metadata().target());
+        write!(
+            out,
+            r#","ph":"X","ts":{:.3},"dur":{:.3},"pid":1,"tid":{},"args":"#,
+            self.timestamp(chrome_span.start),
+            end.saturating_duration_since(chrome_span.start)
+                .as_secs_f64()
+                * 1e6,
+            thread_id(&id, &ctx)
+        )
+        .unwrap();
+        chrome_span.fields.write_json(&mut out);
+        out.push('}');
+        self.write(&out);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ChromeTraceLayer, ClaroMetricsLayer, CLARO_ROUND_SPAN, CLARO_SOLVER_SPAN};
+    use crate::claro::{ClaroSolver, Decision, Vote};
+    use crate::query::NodeQuery;
+    use crate::testing::configuration;
+    use crate::testing::query::responses;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::Registry;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Run a solver to decision, the first round growing the query, returning the rounds run
+    fn decide() -> u64 {
+        let configuration = configuration();
+        let mut solver =
+            ClaroSolver::new(true, configuration, NodeQuery::new(10, "0".into())).unwrap();
+        solver
+            .step(true, &responses(&[Vote::Yes(true), Vote::No(true)]))
+            .unwrap();
+        let mut rounds = 1;
+        while let Decision::Undecided(_) = solver.decision() {
+            solver
+                .step(true, &responses(&[Vote::Yes(true); 20]))
+                .unwrap();
+            rounds += 1;
+        }
+        rounds
+    }
+
+    #[test]
+    fn metrics_aggregate_rounds() {
+        let layer = ClaroMetricsLayer::new();
+        let subscriber = Registry::default().with(layer.clone());
+        let rounds = tracing::subscriber::with_default(subscriber, || decide() + decide());
+        let metrics = layer.metrics();
+        assert_eq!(metrics.rounds, rounds);
+        assert_eq!(metrics.query_growths, 2);
+        assert_eq!(metrics.rounds_to_decision.len(), 2);
+        assert_eq!(metrics.mean_rounds_to_decision(), Some(rounds as f64 / 2.0));
+    }
+
+    #[test]
+    fn chrome_trace_export() {
+        let buffer = SharedBuffer::default();
+        let (layer, guard) = ChromeTraceLayer::new(buffer.clone());
+        let subscriber = Registry::default().with(layer);
+        let rounds = tracing::subscriber::with_default(subscriber, decide);
+        drop(guard);
+
+        let trace: serde_json::Value = serde_json::from_slice(&buffer.0.lock().unwrap()).unwrap();
+        let events = trace.as_array().unwrap();
+        let spans = |name: &str| -> Vec<&serde_json::Value> {
+            events
+                .iter()
+                .filter(|event| event["ph"] == "X" && event["name"] == name)
+                .collect()
+        };
+        let round_spans = spans(CLARO_ROUND_SPAN);
+        assert_eq!(round_spans.len() as u64, rounds);
+        assert_eq!(round_spans[0]["args"]["query_grew"], true);
+        assert_eq!(round_spans.last().unwrap()["args"]["decided"], true);
+        let solver_spans = spans(CLARO_SOLVER_SPAN);
+        assert_eq!(solver_spans.len(), 1);
+        assert!(round_spans
+            .iter()
+            .all(|span| span["tid"] == solver_spans[0]["tid"]));
+        assert!(events
+            .iter()
+            .any(|event| event["ph"] == "M" && event["args"]["name"] == "claro_solver 0"));
+    }
+}
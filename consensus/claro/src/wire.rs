@@ -0,0 +1,210 @@
+// std
+use std::io;
+// crates
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+// internal
+use crate::claro::Vote;
+use crate::query::NodeId;
+use crate::responder::{VoteAnswer, VoteRequest};
+
+/// Max accepted frame payload size, in bytes
+pub const MAX_FRAME_SIZE: usize = 1 << 20;
+
+const REQUEST_TAG: u8 = 0x01;
+const ANSWER_TAG: u8 = 0x02;
+
+const YES: u8 = 0x00;
+const NO: u8 = 0x01;
+const ABSTAIN: u8 = 0x02;
+const RATE_LIMITED: u8 = 0x03;
+
+/// Binary encoding of transaction ids over the wire
+pub trait WireTx: Sized {
+    fn encode(&self, buf: &mut Vec<u8>);
+    /// Decode from the whole of `bytes`
+    fn decode(bytes: &[u8]) -> io::Result<Self>;
+}
+
+impl WireTx for bool {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8);
+    }
+
+    fn decode(bytes: &[u8]) -> io::Result<Self> {
+        match bytes {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => Err(invalid("bool tx")),
+        }
+    }
+}
+
+macro_rules! int_wire_tx {
+    ($($int:ty),*) => {
+        $(
+            impl WireTx for $int {
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_be_bytes());
+                }
+
+                fn decode(bytes: &[u8]) -> io::Result<Self> {
+                    bytes
+                        .try_into()
+                        .map(<$int>::from_be_bytes)
+                        .map_err(|_| invalid(stringify!($int tx)))
+                }
+            }
+        )*
+    };
+}
+
+int_wire_tx!(u32, u64);
+
+impl WireTx for Vec<u8> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+
+    fn decode(bytes: &[u8]) -> io::Result<Self> {
+        Ok(bytes.to_vec())
+    }
+}
+
+impl WireTx for String {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> io::Result<Self> {
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid("utf8 tx"))
+    }
+}
+
+/// Request payload: tag, requester id length (u16 BE), requester id, tx
+pub fn encode_request<Tx: WireTx>(request: &VoteRequest<Tx>) -> io::Result<Vec<u8>> {
+    let requester = request.requester.as_bytes();
+    let requester_len =
+        u16::try_from(requester.len()).map_err(|_| invalid("requester id too long"))?;
+    let mut buf = vec![REQUEST_TAG];
+    buf.extend_from_slice(&requester_len.to_be_bytes());
+    buf.extend_from_slice(requester);
+    request.tx.encode(&mut buf);
+    Ok(buf)
+}
+
+pub fn decode_request<Tx: WireTx>(payload: &[u8]) -> io::Result<VoteRequest<Tx>> {
+    match payload {
+        [REQUEST_TAG, len_hi, len_lo, rest @ ..] => {
+            let len = u16::from_be_bytes([*len_hi, *len_lo]) as usize;
+            if rest.len() < len {
+                return Err(invalid("truncated requester id"));
+            }
+            let (requester, tx) = rest.split_at(len);
+            let requester: NodeId =
+                String::from_utf8(requester.to_vec()).map_err(|_| invalid("utf8 requester id"))?;
+            Ok(VoteRequest {
+                tx: Tx::decode(tx)?,
+                requester,
+            })
+        }
+        _ => Err(invalid("request frame")),
+    }
+}
+
+/// Answer payload: tag, answer kind, tx for votes
+pub fn encode_answer<Tx: WireTx>(answer: &VoteAnswer<Tx>) -> Vec<u8> {
+    let mut buf = vec![ANSWER_TAG];
+    match answer {
+        VoteAnswer::Vote(Vote::Yes(tx)) => {
+            buf.push(YES);
+            tx.encode(&mut buf);
+        }
+        VoteAnswer::Vote(Vote::No(tx)) => {
+            buf.push(NO);
+            tx.encode(&mut buf);
+        }
+        VoteAnswer::Abstain => buf.push(ABSTAIN),
+        VoteAnswer::RateLimited => buf.push(RATE_LIMITED),
+    }
+    buf
+}
+
+pub fn decode_answer<Tx: WireTx>(payload: &[u8]) -> io::Result<VoteAnswer<Tx>> {
+    match payload {
+        [ANSWER_TAG, YES, tx @ ..] => Ok(VoteAnswer::Vote(Vote::Yes(Tx::decode(tx)?))),
+        [ANSWER_TAG, NO, tx @ ..] => Ok(VoteAnswer::Vote(Vote::No(Tx::decode(tx)?))),
+        [ANSWER_TAG, ABSTAIN] => Ok(VoteAnswer::Abstain),
+        [ANSWER_TAG, RATE_LIMITED] => Ok(VoteAnswer::RateLimited),
+        _ => Err(invalid("answer frame")),
+    }
+}
+
+/// Write `payload` prefixed with its length (u32 BE)
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, payload: &[u8]) -> io::Result<()> {
+    if payload.len() > MAX_FRAME_SIZE {
+        return Err(invalid("frame too large"));
+    }
+    writer
+        .write_all(&(payload.len() as u32).to_be_bytes())
+        .await?;
+    writer.write_all(payload).await?;
+    writer.flush().await
+}
+
+/// Read a single length prefixed frame payload
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len).await?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(invalid("frame too large"));
+    }
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload).await?;
+    Ok(payload)
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid {what}"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::claro::Vote;
+    use crate::responder::{VoteAnswer, VoteRequest};
+    use crate::wire::*;
+
+    #[test]
+    fn request_round_trip() {
+        let request = VoteRequest {
+            tx: 42u64,
+            requester: "node_1".to_string(),
+        };
+        let payload = encode_request(&request).unwrap();
+        assert_eq!(decode_request::<u64>(&payload).unwrap(), request);
+        assert!(decode_request::<u64>(&payload[..payload.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn answer_round_trip() {
+        for answer in [
+            VoteAnswer::Vote(Vote::Yes("tx".to_string())),
+            VoteAnswer::Vote(Vote::No("tx".to_string())),
+            VoteAnswer::Abstain,
+            VoteAnswer::RateLimited,
+        ] {
+            assert_eq!(decode_answer(&encode_answer(&answer)).unwrap(), answer);
+        }
+        assert!(decode_answer::<bool>(&[0x02, 0x07]).is_err());
+    }
+
+    #[tokio::test]
+    async fn frames() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"hello").await.unwrap();
+        assert_eq!(&buf[..4], &5u32.to_be_bytes());
+        assert_eq!(read_frame(&mut buf.as_slice()).await.unwrap(), b"hello");
+        let too_large = (MAX_FRAME_SIZE as u32 + 1).to_be_bytes();
+        assert!(read_frame(&mut too_large.as_slice()).await.is_err());
+    }
+}
@@ -0,0 +1,27 @@
+// std
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+// crates
+// internal
+
+/// Snowball solver failures
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SnowballError {
+    /// Solver was stepped after reaching a decision
+    AlreadyDecided,
+    /// Configuration values are out of range
+    InvalidConfiguration(String),
+}
+
+impl Display for SnowballError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnowballError::AlreadyDecided => write!(f, "solver is already decided"),
+            SnowballError::InvalidConfiguration(reason) => {
+                write!(f, "invalid configuration: {reason}")
+            }
+        }
+    }
+}
+
+impl Error for SnowballError {}
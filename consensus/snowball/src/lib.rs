@@ -1,7 +1,16 @@
+mod error;
+mod slush;
 #[allow(dead_code)]
 mod snowball;
+mod snowflake;
 
-pub use crate::snowball::{SnowballConfiguration, SnowballSolver};
+pub use crate::error::SnowballError;
+pub use crate::slush::{SlushConfiguration, SlushSolver};
+pub use crate::snowball::{
+    MultiSnowballSnapshot, MultiSnowballSolver, SnowballConfidence, SnowballConfiguration,
+    SnowballSnapshot, SnowballSolver,
+};
+pub use crate::snowflake::{SnowflakeConfiguration, SnowflakeSolver};
 
 /// Snowball logging filtering tag
 pub const SNOWBALL_TARGET_TAG: &str = "SNOWBALL_TARGET";
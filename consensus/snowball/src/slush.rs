@@ -0,0 +1,260 @@
+use crate::error::SnowballError;
+use claro::{
+    BootstrapPolicy, ConsensusSolver, Decision, NodeQuery, NodeWeight, Opinion, QueryResponse, Vote,
+};
+use std::fmt::Debug;
+
+/// Slush algorithm configuration
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SlushConfiguration {
+    pub quorum_size: usize,
+    pub sample_size: usize,
+    /// Number of rounds to run before accepting the current opinion, `m` as per the slush algorithm
+    pub rounds: usize,
+    /// How nodes without an opinion adopt one, [`BootstrapPolicy::Quorum`] waiting for a value to
+    /// get `quorum_size` votes
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bootstrap: BootstrapPolicy,
+}
+
+impl SlushConfiguration {
+    /// Check that the quorum can be reached within a sample, and by a single opinion only, and
+    /// that at least a round is run
+    pub fn validate(&self) -> Result<(), SnowballError> {
+        if self.quorum_size <= self.sample_size / 2 || self.quorum_size > self.sample_size {
+            return Err(SnowballError::InvalidConfiguration(format!(
+                "quorum_size must be within (sample_size / 2, sample_size = {}], got {}",
+                self.sample_size, self.quorum_size
+            )));
+        }
+        if self.rounds == 0 {
+            return Err(SnowballError::InvalidConfiguration(
+                "rounds must be at least 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Slush computation object
+/// Adopts whatever opinion reaches a quorum in each round, accepts its opinion after a fixed number
+/// of rounds. There is no decision counter involved.
+pub struct SlushSolver<Tx> {
+    configuration: SlushConfiguration,
+    decision: Decision<Tx>,
+    round: usize,
+    node_query: NodeQuery,
+}
+
+impl<Tx: Clone + Debug> SlushSolver<Tx> {
+    /// Build a solver for `tx`, failing on an invalid `configuration`
+    pub fn new(
+        tx: Tx,
+        configuration: SlushConfiguration,
+        node_query: NodeQuery,
+    ) -> Result<Self, SnowballError> {
+        Self::with_initial_opinion(configuration, node_query, Opinion::None(tx))
+    }
+
+    /// Build a solver starting from `opinion`, failing on an invalid `configuration`
+    pub fn with_initial_opinion(
+        configuration: SlushConfiguration,
+        node_query: NodeQuery,
+        opinion: Opinion<Tx>,
+    ) -> Result<Self, SnowballError> {
+        configuration.validate()?;
+        Ok(Self {
+            configuration,
+            decision: Decision::Undecided(opinion),
+            round: 0,
+            node_query,
+        })
+    }
+
+    /// Compute a single round, missing responses are ignored
+    pub fn step(&mut self, responses: &[QueryResponse<Tx>]) -> Result<(), SnowballError> {
+        if let Decision::Decided(_) = self.decision {
+            return Err(SnowballError::AlreadyDecided);
+        }
+        let votes: Vec<&Vote<Tx>> = responses
+            .iter()
+            .filter_map(QueryResponse::as_vote)
+            .collect();
+        // nodes without an opinion do not consume rounds until they adopt one
+        if let Opinion::None(tx) = self.opinion() {
+            let preferences: Vec<(bool, NodeWeight)> =
+                votes.iter().map(|vote| (vote.preference(), 1.0)).collect();
+            let quorum = self.configuration.quorum_size as NodeWeight;
+            match self.configuration.bootstrap.adopt(&preferences, quorum) {
+                Some(preference) => {
+                    self.decision =
+                        Decision::Undecided(Opinion::from_preference(tx, Some(preference)))
+                }
+                None => return Ok(()),
+            }
+        }
+
+        let yes_count = votes.iter().filter(|v| matches!(v, Vote::Yes(_))).count();
+        let no_count = votes.len() - yes_count;
+        for (vote_yes, count) in [(true, yes_count), (false, no_count)] {
+            let opinion_yes = matches!(self.opinion(), Opinion::Yes(_));
+            if count >= self.configuration.quorum_size && vote_yes != opinion_yes {
+                self.decision = Decision::Undecided(self.opinion().flip());
+            }
+        }
+
+        self.round += 1;
+        if self.round >= self.configuration.rounds {
+            self.decision = Decision::Decided(self.opinion());
+        }
+        Ok(())
+    }
+
+    /// Rounds run so far
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    pub fn decision(&self) -> Decision<Tx> {
+        self.decision.clone()
+    }
+
+    pub fn opinion(&self) -> Opinion<Tx> {
+        match &self.decision {
+            Decision::Decided(o) | Decision::Undecided(o) => o.clone(),
+        }
+    }
+
+    /// Derive vote from it's current decision
+    pub fn vote(&self) -> Option<Vote<Tx>> {
+        self.decision().into()
+    }
+
+    pub fn node_query(&self) -> &NodeQuery {
+        &self.node_query
+    }
+}
+
+impl<Tx: Clone + Debug> ConsensusSolver<Tx> for SlushSolver<Tx> {
+    type Error = SnowballError;
+
+    /// Slush votes already carry the transaction, so `tx` is ignored
+    fn step(&mut self, _tx: Tx, responses: &[QueryResponse<Tx>]) -> Result<(), Self::Error> {
+        SlushSolver::step(self, responses)
+    }
+
+    fn decision(&self) -> Decision<Tx> {
+        SlushSolver::decision(self)
+    }
+
+    fn node_query(&self) -> &NodeQuery {
+        SlushSolver::node_query(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SlushConfiguration, SlushSolver};
+    use crate::error::SnowballError;
+    use claro::testing::query::responses;
+    use claro::{BootstrapPolicy, Decision, NodeQuery, Opinion, Vote};
+
+    #[test]
+    fn test_adopts_quorum_opinion() {
+        let configuration = SlushConfiguration {
+            quorum_size: 6,
+            sample_size: 10,
+            rounds: 10,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+
+        let mut solver = SlushSolver::with_initial_opinion(
+            configuration,
+            NodeQuery::new(0, "0".to_string()),
+            Opinion::Yes(true),
+        )
+        .unwrap();
+
+        solver.step(&responses(&[Vote::No(true); 10])).unwrap();
+        assert_eq!(solver.decision(), Decision::Undecided(Opinion::No(true)));
+
+        let mut split = vec![Vote::Yes(true); 5];
+        split.extend([Vote::No(true); 5]);
+        solver.step(&responses(&split)).unwrap();
+        assert_eq!(solver.decision(), Decision::Undecided(Opinion::No(true)));
+        assert_eq!(solver.round(), 2);
+    }
+
+    #[test]
+    fn test_quorum_must_be_a_majority() {
+        let configuration = |quorum_size| SlushConfiguration {
+            quorum_size,
+            sample_size: 10,
+            rounds: 10,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+        assert!(configuration(0).validate().is_err());
+        // both opinions could reach a quorum of 5 out of 10 at once
+        assert!(configuration(5).validate().is_err());
+        assert!(configuration(6).validate().is_ok());
+        assert!(configuration(10).validate().is_ok());
+        assert!(configuration(11).validate().is_err());
+        assert!(SlushConfiguration {
+            rounds: 0,
+            ..configuration(6)
+        }
+        .validate()
+        .is_err());
+        assert!(matches!(
+            SlushSolver::new(true, configuration(5), NodeQuery::new(10, "0".to_string())),
+            Err(SnowballError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn test_decides_after_rounds() {
+        let configuration = SlushConfiguration {
+            quorum_size: 6,
+            sample_size: 10,
+            rounds: 3,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+
+        let mut solver = SlushSolver::with_initial_opinion(
+            configuration,
+            NodeQuery::new(0, "0".to_string()),
+            Opinion::No(true),
+        )
+        .unwrap();
+
+        for _ in 0..3 {
+            solver.step(&responses(&[Vote::Yes(true); 10])).unwrap();
+        }
+        assert_eq!(solver.decision(), Decision::Decided(Opinion::Yes(true)));
+    }
+
+    #[test]
+    fn test_opinionless_node_adopts_and_decides() {
+        let configuration = SlushConfiguration {
+            quorum_size: 6,
+            sample_size: 10,
+            rounds: 3,
+            bootstrap: BootstrapPolicy::Majority,
+        };
+
+        let mut solver =
+            SlushSolver::new(true, configuration, NodeQuery::new(0, "0".to_string())).unwrap();
+
+        let mut votes = vec![Vote::No(true); 7];
+        votes.extend([Vote::Yes(true); 3]);
+        solver.step(&responses(&votes)).unwrap();
+        assert_eq!(solver.decision(), Decision::Undecided(Opinion::No(true)));
+        assert_eq!(solver.round(), 1);
+
+        for _ in 0..2 {
+            solver.step(&responses(&votes)).unwrap();
+        }
+        assert_eq!(solver.decision(), Decision::Decided(Opinion::No(true)));
+    }
+}
@@ -1,133 +1,386 @@
-use claro::{Decision, NodeQuery, Opinion, Vote};
+use crate::error::SnowballError;
+use claro::{
+    BootstrapPolicy, ConsensusSolver, Decision, MultiDecision, NodeQuery, NodeWeight, Opinion,
+    QueryResponse, Vote,
+};
 use std::fmt::Debug;
 
 /// Snowball algorithm configuration
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SnowballConfiguration {
     pub quorum_size: usize,
     pub sample_size: usize,
     pub decision_threshold: usize,
+    /// How nodes without an opinion adopt one, [`BootstrapPolicy::Quorum`] waiting for a value to
+    /// get `quorum_size` votes
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bootstrap: BootstrapPolicy,
 }
 
-/// Snowball computation object
-pub struct SnowballSolver<Tx> {
+impl SnowballConfiguration {
+    /// Check that the quorum can be reached within a sample, and by a single value only
+    pub fn validate(&self) -> Result<(), SnowballError> {
+        if self.quorum_size <= self.sample_size / 2 || self.quorum_size > self.sample_size {
+            return Err(SnowballError::InvalidConfiguration(format!(
+                "quorum_size must be within (sample_size / 2, sample_size = {}], got {}",
+                self.sample_size, self.quorum_size
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Snowball cumulative per opinion confidence counters
+/// Each counter is incremented every time a query reaches a quorum for that opinion.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnowballConfidence {
+    pub yes: u64,
+    pub no: u64,
+}
+
+/// [`MultiSnowballSolver`] in-flight state, see [`MultiSnowballSolver::snapshot`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultiSnowballSnapshot<V> {
+    pub configuration: SnowballConfiguration,
+    pub decision: MultiDecision<V>,
+    pub consecutive_success: u64,
+    /// Cumulative per value confidence counters
+    pub confidence: Vec<(V, u64)>,
+    pub last_value: Option<V>,
+    pub node_query: NodeQuery,
+}
+
+/// Snowball computation object over a conflict set of `k` values
+/// Keeps a confidence counter per value and only switches its preference when the counter of
+/// another value gets ahead.
+pub struct MultiSnowballSolver<V> {
     configuration: SnowballConfiguration,
-    decision: Decision<Tx>,
+    decision: MultiDecision<V>,
+    /// Consecutive queries reaching a quorum for [`MultiSnowballSolver::last_value`]
     consecutive_success: u64,
+    /// Cumulative per value confidence counters, in the order values were first known
+    confidence: Vec<(V, u64)>,
+    /// Value of the last query that reached a quorum
+    last_value: Option<V>,
     node_query: NodeQuery,
 }
 
-impl<Tx: Clone + Debug> SnowballSolver<Tx> {
-    pub fn new(tx: Tx, configuration: SnowballConfiguration, node_query: NodeQuery) -> Self {
-        Self {
+impl<V: Clone + PartialEq + Debug> MultiSnowballSolver<V> {
+    /// Build a solver over `conflict_set`, values not in it are learned as votes arrive
+    /// Fails on an invalid `configuration`
+    pub fn new(
+        conflict_set: impl IntoIterator<Item = V>,
+        configuration: SnowballConfiguration,
+        node_query: NodeQuery,
+        preference: Option<V>,
+    ) -> Result<Self, SnowballError> {
+        configuration.validate()?;
+        let mut solver = Self {
             configuration,
-            decision: Decision::Undecided(Opinion::None(tx)),
+            decision: MultiDecision::Undecided(preference.clone()),
             consecutive_success: 0,
+            confidence: Vec::new(),
+            last_value: preference.clone(),
             node_query,
+        };
+        for value in conflict_set.into_iter().chain(preference) {
+            solver.confidence_entry(&value);
         }
+        Ok(solver)
     }
 
-    pub fn with_initial_opinion(
-        configuration: SnowballConfiguration,
-        node_query: NodeQuery,
-        opinion: Opinion<Tx>,
-    ) -> Self {
-        Self {
+    /// Resume a solver from a previously taken snapshot
+    /// Fails if the snapshot configuration does not [`SnowballConfiguration::validate`]
+    pub fn restore(snapshot: MultiSnowballSnapshot<V>) -> Result<Self, SnowballError> {
+        let MultiSnowballSnapshot {
             configuration,
-            decision: Decision::Undecided(opinion),
-            consecutive_success: 0,
+            decision,
+            consecutive_success,
+            confidence,
+            last_value,
             node_query,
+        } = snapshot;
+        configuration.validate()?;
+        Ok(Self {
+            configuration,
+            decision,
+            consecutive_success,
+            confidence,
+            last_value,
+            node_query,
+        })
+    }
+
+    /// Copy of the in-flight state, enough to [`MultiSnowballSolver::restore`] it later on
+    pub fn snapshot(&self) -> MultiSnowballSnapshot<V> {
+        MultiSnowballSnapshot {
+            configuration: self.configuration,
+            decision: self.decision.clone(),
+            consecutive_success: self.consecutive_success,
+            confidence: self.confidence.clone(),
+            last_value: self.last_value.clone(),
+            node_query: self.node_query.clone(),
         }
     }
 
-    fn count_opinion_votes(&self, votes: &[Vote<Tx>]) -> usize {
-        votes
-            .iter()
-            .filter(|v| {
-                matches!(
-                    (v, self.vote()),
-                    (Vote::Yes(_), Some(Vote::Yes(_))) | (Vote::No(_), Some(Vote::No(_)))
-                )
-            })
-            .count()
+    /// Learn a new conflict set value, the current preference is kept
+    pub fn insert_value(&mut self, value: &V) {
+        self.confidence_entry(value);
     }
 
-    pub fn step(&mut self, votes: &[Vote<Tx>]) {
-        assert!(matches!(self.decision, Decision::Undecided(_)));
+    fn confidence_entry(&mut self, value: &V) -> &mut u64 {
+        let position = match self.confidence.iter().position(|(v, _)| v == value) {
+            Some(position) => position,
+            None => {
+                self.confidence.push((value.clone(), 0));
+                self.confidence.len() - 1
+            }
+        };
+        &mut self.confidence[position].1
+    }
 
-        let preference_count = self.count_opinion_votes(votes);
-        let not_preference_count = votes.len() - preference_count;
+    pub fn step(&mut self, votes: &[V]) -> Result<(), SnowballError> {
+        if self.decision.is_decided() {
+            return Err(SnowballError::AlreadyDecided);
+        }
+        let mut preference = match self.preference() {
+            Some(preference) => preference,
+            None => match self.bootstrap(votes) {
+                Some(preference) => preference,
+                None => return Ok(()),
+            },
+        };
+        for vote in votes {
+            self.confidence_entry(vote);
+        }
 
-        if preference_count >= self.configuration.quorum_size {
-            self.consecutive_success += 1;
-        } else if not_preference_count >= self.configuration.quorum_size {
-            self.decision = Decision::Undecided(self.opinion().flip());
-            self.consecutive_success = 1;
-        } else {
-            self.consecutive_success = 0
+        let mut quorum = false;
+        for index in 0..self.confidence.len() {
+            let value = self.confidence[index].0.clone();
+            let count = votes.iter().filter(|&v| v == &value).count();
+            if count < self.configuration.quorum_size {
+                continue;
+            }
+            quorum = true;
+            self.confidence[index].1 += 1;
+            if self.confidence_for(&value) > self.confidence_for(&preference) {
+                preference = value.clone();
+                self.decision = MultiDecision::Undecided(Some(preference.clone()));
+            }
+            if self.last_value.as_ref() == Some(&value) {
+                self.consecutive_success += 1;
+            } else {
+                self.last_value = Some(value);
+                self.consecutive_success = 1;
+            }
+        }
+        if !quorum {
+            self.consecutive_success = 0;
         }
 
+        // The streak belongs to `last_value`, which may trail the preference counters
         if self.consecutive_success > self.configuration.decision_threshold as u64 {
-            self.decision = Decision::Decided(self.opinion())
+            self.decision = MultiDecision::Decided(self.last_value.clone())
         }
+        Ok(())
+    }
+
+    /// Adopt a preference out of `votes` following [`SnowballConfiguration::bootstrap`]
+    fn bootstrap(&mut self, votes: &[V]) -> Option<V> {
+        let votes: Vec<(V, NodeWeight)> = votes.iter().map(|vote| (vote.clone(), 1.0)).collect();
+        let quorum = self.configuration.quorum_size as NodeWeight;
+        let preference = self.configuration.bootstrap.adopt(&votes, quorum)?;
+        self.decision = MultiDecision::Undecided(Some(preference.clone()));
+        Some(preference)
     }
 
     pub fn consecutive_success(&self) -> u64 {
         self.consecutive_success
     }
 
-    pub fn decision(&self) -> Decision<Tx> {
+    /// Times a query reached a quorum for `value`
+    pub fn confidence_for(&self, value: &V) -> u64 {
+        self.confidence
+            .iter()
+            .find_map(|(v, confidence)| (v == value).then_some(*confidence))
+            .unwrap_or_default()
+    }
+
+    pub fn decision(&self) -> MultiDecision<V> {
         self.decision.clone()
     }
 
-    pub fn opinion(&self) -> Opinion<Tx> {
-        match &self.decision {
-            Decision::Decided(o) | Decision::Undecided(o) => o.clone(),
+    pub fn preference(&self) -> Option<V> {
+        self.decision.preference().cloned()
+    }
+
+    /// Derive vote from it's current decision
+    pub fn vote(&self) -> Option<V> {
+        self.preference()
+    }
+
+    pub fn node_query(&self) -> &NodeQuery {
+        &self.node_query
+    }
+}
+
+/// [`SnowballSolver`] in-flight state, see [`SnowballSolver::snapshot`]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnowballSnapshot<Tx> {
+    pub tx: Tx,
+    pub solver: MultiSnowballSnapshot<bool>,
+}
+
+/// Snowball computation object
+/// Binary specialization of [`MultiSnowballSolver`] over a `{yes, no}` conflict set.
+pub struct SnowballSolver<Tx> {
+    /// Tx being decided
+    tx: Tx,
+    /// Conflict set solver, `true` stands for yes
+    solver: MultiSnowballSolver<bool>,
+}
+
+impl<Tx: Clone + Debug> SnowballSolver<Tx> {
+    /// Build a solver for `tx`, failing on an invalid `configuration`
+    pub fn new(
+        tx: Tx,
+        configuration: SnowballConfiguration,
+        node_query: NodeQuery,
+    ) -> Result<Self, SnowballError> {
+        Self::with_initial_opinion(configuration, node_query, Opinion::None(tx))
+    }
+
+    /// Build a solver starting from `opinion`, failing on an invalid `configuration`
+    pub fn with_initial_opinion(
+        configuration: SnowballConfiguration,
+        node_query: NodeQuery,
+        opinion: Opinion<Tx>,
+    ) -> Result<Self, SnowballError> {
+        let preference = opinion.preference();
+        Ok(Self {
+            tx: opinion.tx().clone(),
+            solver: MultiSnowballSolver::new([true, false], configuration, node_query, preference)?,
+        })
+    }
+
+    /// Resume a solver from a previously taken snapshot
+    /// Fails if the snapshot configuration does not [`SnowballConfiguration::validate`]
+    pub fn restore(snapshot: SnowballSnapshot<Tx>) -> Result<Self, SnowballError> {
+        Ok(Self {
+            tx: snapshot.tx,
+            solver: MultiSnowballSolver::restore(snapshot.solver)?,
+        })
+    }
+
+    /// Copy of the in-flight state, enough to [`SnowballSolver::restore`] it later on
+    pub fn snapshot(&self) -> SnowballSnapshot<Tx> {
+        SnowballSnapshot {
+            tx: self.tx.clone(),
+            solver: self.solver.snapshot(),
+        }
+    }
+
+    /// Compute a single round, missing responses are ignored
+    pub fn step(&mut self, responses: &[QueryResponse<Tx>]) -> Result<(), SnowballError> {
+        let votes: Vec<bool> = responses
+            .iter()
+            .filter_map(QueryResponse::as_vote)
+            .map(Vote::preference)
+            .collect();
+        self.solver.step(&votes)
+    }
+
+    pub fn consecutive_success(&self) -> u64 {
+        self.solver.consecutive_success()
+    }
+
+    /// Cumulative per opinion confidence counters
+    pub fn confidence(&self) -> SnowballConfidence {
+        SnowballConfidence {
+            yes: self.solver.confidence_for(&true),
+            no: self.solver.confidence_for(&false),
         }
     }
 
+    pub fn decision(&self) -> Decision<Tx> {
+        Decision::from_multi(self.tx.clone(), self.solver.decision())
+    }
+
+    pub fn opinion(&self) -> Opinion<Tx> {
+        Opinion::from_preference(self.tx.clone(), self.solver.preference())
+    }
+
     /// Derive vote from it's current decision
     pub fn vote(&self) -> Option<Vote<Tx>> {
         self.decision().into()
     }
 
     pub fn node_query(&self) -> &NodeQuery {
-        &self.node_query
+        self.solver.node_query()
+    }
+}
+
+impl<Tx: Clone + Debug> ConsensusSolver<Tx> for SnowballSolver<Tx> {
+    type Error = SnowballError;
+
+    /// Snowball votes already carry the transaction, so `tx` is ignored
+    fn step(&mut self, _tx: Tx, responses: &[QueryResponse<Tx>]) -> Result<(), Self::Error> {
+        SnowballSolver::step(self, responses)
+    }
+
+    fn decision(&self) -> Decision<Tx> {
+        SnowballSolver::decision(self)
+    }
+
+    fn node_query(&self) -> &NodeQuery {
+        SnowballSolver::node_query(self)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{SnowballConfiguration, SnowballSolver};
-    use claro::{Decision, NodeQuery, Opinion, Vote};
+    use super::{MultiSnowballSolver, SnowballConfidence, SnowballConfiguration, SnowballSolver};
+    use crate::error::SnowballError;
+    use claro::testing::query::responses;
+    use claro::{
+        BootstrapPolicy, ConsensusSolver, Decision, MultiDecision, NodeQuery, Opinion,
+        QueryResponse, Vote,
+    };
 
     #[test]
     fn test_change_opinion() {
         let configuration = SnowballConfiguration {
-            quorum_size: 1,
+            quorum_size: 6,
             sample_size: 10,
             decision_threshold: 10,
+            bootstrap: BootstrapPolicy::FirstVote,
         };
 
         let mut solver = SnowballSolver::with_initial_opinion(
             configuration,
             NodeQuery::new(0, "0".to_string()),
             Opinion::Yes(true),
-        );
+        )
+        .unwrap();
 
-        let votes = vec![Vote::No(true); 10];
-        solver.step(&votes);
+        let votes = responses(&[Vote::No(true); 10]);
+        solver.step(&votes).unwrap();
         assert!(matches!(solver.decision(), Decision::Undecided(_)));
-        assert_eq!(solver.consecutive_success, 1);
+        assert_eq!(solver.consecutive_success(), 1);
         assert_eq!(solver.opinion(), Opinion::No(true));
     }
 
     #[test]
     fn test_makes_decision() {
         let configuration = SnowballConfiguration {
-            quorum_size: 1,
+            quorum_size: 6,
             sample_size: 10,
             decision_threshold: 10,
+            bootstrap: BootstrapPolicy::FirstVote,
         };
         let beta = configuration.decision_threshold;
 
@@ -135,38 +388,337 @@ mod test {
             configuration,
             NodeQuery::new(0, "0".to_string()),
             Opinion::Yes(true),
-        );
+        )
+        .unwrap();
 
-        let votes = vec![Vote::No(true); 10];
+        let votes = responses(&[Vote::No(true); 10]);
         for _ in 0..beta + 1 {
-            solver.step(&votes);
+            solver.step(&votes).unwrap();
         }
 
         assert_eq!(solver.decision(), Decision::Decided(Opinion::No(true)));
-        assert_eq!(solver.consecutive_success, beta as u64 + 1);
+        assert_eq!(solver.consecutive_success(), beta as u64 + 1);
         assert_eq!(solver.opinion(), Opinion::No(true));
     }
 
     #[test]
     fn test_reset_consecutive_counter() {
         let configuration = SnowballConfiguration {
-            quorum_size: 2,
+            quorum_size: 6,
             sample_size: 10,
             decision_threshold: 10,
+            bootstrap: BootstrapPolicy::FirstVote,
         };
 
         let mut solver = SnowballSolver::with_initial_opinion(
             configuration,
             NodeQuery::new(0, "0".to_string()),
             Opinion::Yes(true),
-        );
+        )
+        .unwrap();
 
-        let votes = vec![Vote::No(true), Vote::Yes(true)];
+        let votes = responses(&[Vote::No(true), Vote::Yes(true)]);
 
-        solver.step(&votes);
+        solver.step(&votes).unwrap();
 
-        assert_eq!(solver.consecutive_success, 0);
+        assert_eq!(solver.consecutive_success(), 0);
         assert_eq!(solver.opinion(), Opinion::Yes(true));
         assert!(matches!(solver.decision(), Decision::Undecided(_)));
     }
+
+    #[test]
+    fn test_step_decided_fails() {
+        let configuration = SnowballConfiguration {
+            quorum_size: 6,
+            sample_size: 10,
+            decision_threshold: 0,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+
+        let mut solver = SnowballSolver::with_initial_opinion(
+            configuration,
+            NodeQuery::new(0, "0".to_string()),
+            Opinion::Yes(true),
+        )
+        .unwrap();
+
+        let votes = responses(&[Vote::Yes(true); 10]);
+        solver.step(&votes).unwrap();
+        assert_eq!(solver.decision(), Decision::Decided(Opinion::Yes(true)));
+        assert_eq!(solver.step(&votes), Err(SnowballError::AlreadyDecided));
+    }
+
+    #[test]
+    fn test_invalid_configuration_is_rejected() {
+        let configuration = SnowballConfiguration {
+            quorum_size: 11,
+            sample_size: 10,
+            decision_threshold: 1,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+        assert!(matches!(
+            SnowballSolver::new(true, configuration, NodeQuery::new(10, "0".to_string())),
+            Err(SnowballError::InvalidConfiguration(_))
+        ));
+        assert!(matches!(
+            MultiSnowballSolver::new(
+                ["red", "blue"],
+                configuration,
+                NodeQuery::new(10, "0".to_string()),
+                None
+            ),
+            Err(SnowballError::InvalidConfiguration(_))
+        ));
+        // several values could reach a quorum of 5 out of 10 at once
+        let minority = SnowballConfiguration {
+            quorum_size: 5,
+            ..configuration
+        };
+        assert!(minority.validate().is_err());
+        assert!(SnowballConfiguration {
+            quorum_size: 6,
+            ..configuration
+        }
+        .validate()
+        .is_ok());
+    }
+
+    fn rounds_to_decision<S: ConsensusSolver<bool>>(
+        solver: &mut S,
+        votes: &[QueryResponse<bool>],
+    ) -> usize {
+        let mut rounds = 0;
+        while matches!(solver.decision(), Decision::Undecided(_)) {
+            solver.step(true, votes).unwrap();
+            rounds += 1;
+        }
+        rounds
+    }
+
+    #[test]
+    fn test_consensus_solver() {
+        let configuration = SnowballConfiguration {
+            quorum_size: 6,
+            sample_size: 10,
+            decision_threshold: 10,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+
+        let mut solver = SnowballSolver::with_initial_opinion(
+            configuration,
+            NodeQuery::new(0, "0".to_string()),
+            Opinion::Yes(true),
+        )
+        .unwrap();
+
+        let votes = responses(&[Vote::Yes(true); 10]);
+        assert_eq!(rounds_to_decision(&mut solver, &votes), 11);
+        assert_eq!(ConsensusSolver::vote(&solver), Some(Vote::Yes(true)));
+    }
+
+    #[test]
+    fn test_keeps_preference_while_ahead() {
+        let configuration = SnowballConfiguration {
+            quorum_size: 6,
+            sample_size: 10,
+            decision_threshold: 10,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+
+        let mut solver = SnowballSolver::with_initial_opinion(
+            configuration,
+            NodeQuery::new(0, "0".to_string()),
+            Opinion::Yes(true),
+        )
+        .unwrap();
+
+        let yes_votes = responses(&[Vote::Yes(true); 10]);
+        let no_votes = responses(&[Vote::No(true); 10]);
+        for _ in 0..3 {
+            solver.step(&yes_votes).unwrap();
+        }
+        assert_eq!(solver.consecutive_success(), 3);
+
+        // a single no quorum is not enough to overtake the yes confidence
+        solver.step(&no_votes).unwrap();
+        assert_eq!(solver.opinion(), Opinion::Yes(true));
+        assert_eq!(solver.consecutive_success(), 1);
+        assert_eq!(solver.confidence(), SnowballConfidence { yes: 3, no: 1 });
+
+        for _ in 0..3 {
+            solver.step(&no_votes).unwrap();
+        }
+        assert_eq!(solver.opinion(), Opinion::No(true));
+        assert_eq!(solver.confidence(), SnowballConfidence { yes: 3, no: 4 });
+    }
+
+    #[test]
+    fn test_multi_colour_quorum() {
+        let configuration = SnowballConfiguration {
+            quorum_size: 3,
+            sample_size: 5,
+            decision_threshold: 1,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+
+        let mut solver = MultiSnowballSolver::new(
+            ["red", "green", "blue"],
+            configuration,
+            NodeQuery::new(0, "0".to_string()),
+            Some("red"),
+        )
+        .unwrap();
+
+        let votes = ["blue", "blue", "blue", "green", "red"];
+        solver.step(&votes).unwrap();
+        assert_eq!(solver.decision(), MultiDecision::Undecided(Some("blue")));
+        assert_eq!(solver.confidence_for(&"blue"), 1);
+        assert_eq!(solver.confidence_for(&"red"), 0);
+
+        solver.step(&votes).unwrap();
+        assert_eq!(solver.decision(), MultiDecision::Decided(Some("blue")));
+        assert_eq!(solver.consecutive_success(), 2);
+    }
+
+    #[test]
+    fn test_decides_streak_colour() {
+        let configuration = SnowballConfiguration {
+            quorum_size: 2,
+            sample_size: 3,
+            decision_threshold: 2,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+
+        let mut solver = MultiSnowballSolver::new(
+            [true, false],
+            configuration,
+            NodeQuery::new(0, "0".to_string()),
+            Some(true),
+        )
+        .unwrap();
+
+        // four yes quorums, never long enough a streak to decide
+        for _ in 0..2 {
+            solver.step(&[true, true]).unwrap();
+            solver.step(&[true, true]).unwrap();
+            solver.step(&[]).unwrap();
+        }
+        // three no quorums in a row while yes keeps the upper hand on the counters
+        for _ in 0..3 {
+            solver.step(&[false, false, false]).unwrap();
+        }
+        assert_eq!(solver.confidence_for(&true), 4);
+        assert_eq!(solver.confidence_for(&false), 3);
+        assert_eq!(solver.consecutive_success(), 3);
+        assert_eq!(solver.decision(), MultiDecision::Decided(Some(false)));
+    }
+
+    #[test]
+    fn test_bootstrap_policies() {
+        let configuration = |bootstrap| SnowballConfiguration {
+            quorum_size: 3,
+            sample_size: 5,
+            decision_threshold: 10,
+            bootstrap,
+        };
+        let split = responses(&[
+            Vote::No(true),
+            Vote::Yes(true),
+            Vote::Yes(true),
+            Vote::No(true),
+            Vote::No(true),
+        ]);
+
+        let mut first_vote = SnowballSolver::new(
+            true,
+            configuration(BootstrapPolicy::FirstVote),
+            NodeQuery::new(5, "0".to_string()),
+        )
+        .unwrap();
+        first_vote.step(&responses(&[Vote::Yes(true)])).unwrap();
+        assert_eq!(first_vote.opinion(), Opinion::Yes(true));
+
+        let mut majority = SnowballSolver::new(
+            true,
+            configuration(BootstrapPolicy::Majority),
+            NodeQuery::new(5, "0".to_string()),
+        )
+        .unwrap();
+        majority.step(&split).unwrap();
+        assert_eq!(majority.opinion(), Opinion::No(true));
+        assert_eq!(majority.confidence(), SnowballConfidence { yes: 0, no: 1 });
+
+        let mut quorum = SnowballSolver::new(
+            true,
+            configuration(BootstrapPolicy::Quorum),
+            NodeQuery::new(5, "0".to_string()),
+        )
+        .unwrap();
+        quorum
+            .step(&responses(&[Vote::Yes(true), Vote::Yes(true)]))
+            .unwrap();
+        assert_eq!(quorum.opinion(), Opinion::None(true));
+        quorum.step(&split).unwrap();
+        assert_eq!(quorum.opinion(), Opinion::No(true));
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let configuration = SnowballConfiguration {
+            quorum_size: 6,
+            sample_size: 10,
+            decision_threshold: 10,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+
+        let mut solver = SnowballSolver::with_initial_opinion(
+            configuration,
+            NodeQuery::new(10, "0".to_string()),
+            Opinion::Yes(true),
+        )
+        .unwrap();
+        for _ in 0..3 {
+            solver.step(&responses(&[Vote::No(true); 10])).unwrap();
+        }
+
+        let mut restored = SnowballSolver::restore(solver.snapshot()).unwrap();
+        assert_eq!(restored.opinion(), Opinion::No(true));
+        assert_eq!(restored.consecutive_success(), 3);
+        assert_eq!(restored.confidence(), SnowballConfidence { yes: 0, no: 3 });
+        solver.step(&responses(&[Vote::No(true); 10])).unwrap();
+        restored.step(&responses(&[Vote::No(true); 10])).unwrap();
+        assert_eq!(restored.consecutive_success(), solver.consecutive_success());
+
+        let mut invalid = solver.snapshot();
+        invalid.solver.configuration.quorum_size = 0;
+        assert!(matches!(
+            SnowballSolver::restore(invalid),
+            Err(SnowballError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_snapshot_serde_round_trip() {
+        let configuration = SnowballConfiguration {
+            quorum_size: 6,
+            sample_size: 10,
+            decision_threshold: 10,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+
+        let mut solver = SnowballSolver::with_initial_opinion(
+            configuration,
+            NodeQuery::new(10, "0".to_string()),
+            Opinion::Yes(true),
+        )
+        .unwrap();
+        solver.step(&responses(&[Vote::Yes(true); 10])).unwrap();
+
+        let serialized = serde_json::to_string(&solver.snapshot()).unwrap();
+        let restored =
+            SnowballSolver::<bool>::restore(serde_json::from_str(&serialized).unwrap()).unwrap();
+        assert_eq!(restored.decision(), solver.decision());
+        assert_eq!(restored.confidence(), SnowballConfidence { yes: 1, no: 0 });
+    }
 }
@@ -0,0 +1,298 @@
+use crate::error::SnowballError;
+use crate::snowball::SnowballConfiguration;
+use claro::{ConsensusSolver, Decision, NodeQuery, NodeWeight, Opinion, QueryResponse, Vote};
+use std::fmt::Debug;
+
+/// Snowflake algorithm configuration, same parameters as Snowball
+pub type SnowflakeConfiguration = SnowballConfiguration;
+
+/// Snowflake computation object
+/// Flips its preference as soon as the opposite opinion reaches a quorum, keeps no memory beyond
+/// the current streak of successful queries.
+pub struct SnowflakeSolver<Tx> {
+    configuration: SnowflakeConfiguration,
+    decision: Decision<Tx>,
+    consecutive_success: u64,
+    node_query: NodeQuery,
+}
+
+impl<Tx: Clone + Debug> SnowflakeSolver<Tx> {
+    /// Build a solver for `tx`, failing on an invalid `configuration`
+    pub fn new(
+        tx: Tx,
+        configuration: SnowflakeConfiguration,
+        node_query: NodeQuery,
+    ) -> Result<Self, SnowballError> {
+        Self::with_initial_opinion(configuration, node_query, Opinion::None(tx))
+    }
+
+    /// Build a solver starting from `opinion`, failing on an invalid `configuration`
+    pub fn with_initial_opinion(
+        configuration: SnowflakeConfiguration,
+        node_query: NodeQuery,
+        opinion: Opinion<Tx>,
+    ) -> Result<Self, SnowballError> {
+        configuration.validate()?;
+        Ok(Self {
+            configuration,
+            decision: Decision::Undecided(opinion),
+            consecutive_success: 0,
+            node_query,
+        })
+    }
+
+    fn count_opinion_votes(&self, votes: &[&Vote<Tx>]) -> usize {
+        votes
+            .iter()
+            .filter(|v| {
+                matches!(
+                    (v, self.vote()),
+                    (Vote::Yes(_), Some(Vote::Yes(_))) | (Vote::No(_), Some(Vote::No(_)))
+                )
+            })
+            .count()
+    }
+
+    /// Compute a single round, missing responses are ignored
+    pub fn step(&mut self, responses: &[QueryResponse<Tx>]) -> Result<(), SnowballError> {
+        if let Decision::Decided(_) = self.decision {
+            return Err(SnowballError::AlreadyDecided);
+        }
+        let votes: Vec<&Vote<Tx>> = responses
+            .iter()
+            .filter_map(QueryResponse::as_vote)
+            .collect();
+        if let Opinion::None(tx) = self.opinion() {
+            let preferences: Vec<(bool, NodeWeight)> =
+                votes.iter().map(|vote| (vote.preference(), 1.0)).collect();
+            let quorum = self.configuration.quorum_size as NodeWeight;
+            match self.configuration.bootstrap.adopt(&preferences, quorum) {
+                Some(preference) => {
+                    self.decision =
+                        Decision::Undecided(Opinion::from_preference(tx, Some(preference)))
+                }
+                None => return Ok(()),
+            }
+        }
+
+        let preference_count = self.count_opinion_votes(&votes);
+        let not_preference_count = votes.len() - preference_count;
+
+        if preference_count >= self.configuration.quorum_size {
+            self.consecutive_success += 1;
+        } else if not_preference_count >= self.configuration.quorum_size {
+            self.decision = Decision::Undecided(self.opinion().flip());
+            self.consecutive_success = 1;
+        } else {
+            self.consecutive_success = 0
+        }
+
+        if self.consecutive_success > self.configuration.decision_threshold as u64 {
+            self.decision = Decision::Decided(self.opinion())
+        }
+        Ok(())
+    }
+
+    pub fn consecutive_success(&self) -> u64 {
+        self.consecutive_success
+    }
+
+    pub fn decision(&self) -> Decision<Tx> {
+        self.decision.clone()
+    }
+
+    pub fn opinion(&self) -> Opinion<Tx> {
+        match &self.decision {
+            Decision::Decided(o) | Decision::Undecided(o) => o.clone(),
+        }
+    }
+
+    /// Derive vote from it's current decision
+    pub fn vote(&self) -> Option<Vote<Tx>> {
+        self.decision().into()
+    }
+
+    pub fn node_query(&self) -> &NodeQuery {
+        &self.node_query
+    }
+}
+
+impl<Tx: Clone + Debug> ConsensusSolver<Tx> for SnowflakeSolver<Tx> {
+    type Error = SnowballError;
+
+    /// Snowflake votes already carry the transaction, so `tx` is ignored
+    fn step(&mut self, _tx: Tx, responses: &[QueryResponse<Tx>]) -> Result<(), Self::Error> {
+        SnowflakeSolver::step(self, responses)
+    }
+
+    fn decision(&self) -> Decision<Tx> {
+        SnowflakeSolver::decision(self)
+    }
+
+    fn node_query(&self) -> &NodeQuery {
+        SnowflakeSolver::node_query(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SnowflakeConfiguration, SnowflakeSolver};
+    use crate::error::SnowballError;
+    use claro::testing::query::responses;
+    use claro::{
+        BootstrapPolicy, ConsensusSolver, Decision, NodeQuery, Opinion, QueryResponse, Vote,
+    };
+
+    #[test]
+    fn test_change_opinion() {
+        let configuration = SnowflakeConfiguration {
+            quorum_size: 6,
+            sample_size: 10,
+            decision_threshold: 10,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+
+        let mut solver = SnowflakeSolver::with_initial_opinion(
+            configuration,
+            NodeQuery::new(0, "0".to_string()),
+            Opinion::Yes(true),
+        )
+        .unwrap();
+
+        let votes = responses(&[Vote::No(true); 10]);
+        solver.step(&votes).unwrap();
+        assert!(matches!(solver.decision(), Decision::Undecided(_)));
+        assert_eq!(solver.consecutive_success, 1);
+        assert_eq!(solver.opinion(), Opinion::No(true));
+    }
+
+    #[test]
+    fn test_makes_decision() {
+        let configuration = SnowflakeConfiguration {
+            quorum_size: 6,
+            sample_size: 10,
+            decision_threshold: 10,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+        let beta = configuration.decision_threshold;
+
+        let mut solver = SnowflakeSolver::with_initial_opinion(
+            configuration,
+            NodeQuery::new(0, "0".to_string()),
+            Opinion::Yes(true),
+        )
+        .unwrap();
+
+        let votes = responses(&[Vote::No(true); 10]);
+        for _ in 0..beta + 1 {
+            solver.step(&votes).unwrap();
+        }
+
+        assert_eq!(solver.decision(), Decision::Decided(Opinion::No(true)));
+        assert_eq!(solver.consecutive_success, beta as u64 + 1);
+        assert_eq!(solver.opinion(), Opinion::No(true));
+    }
+
+    #[test]
+    fn test_reset_consecutive_counter() {
+        let configuration = SnowflakeConfiguration {
+            quorum_size: 6,
+            sample_size: 10,
+            decision_threshold: 10,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+
+        let mut solver = SnowflakeSolver::with_initial_opinion(
+            configuration,
+            NodeQuery::new(0, "0".to_string()),
+            Opinion::Yes(true),
+        )
+        .unwrap();
+
+        let votes = responses(&[Vote::No(true), Vote::Yes(true)]);
+
+        solver.step(&votes).unwrap();
+
+        assert_eq!(solver.consecutive_success, 0);
+        assert_eq!(solver.opinion(), Opinion::Yes(true));
+        assert!(matches!(solver.decision(), Decision::Undecided(_)));
+    }
+
+    #[test]
+    fn test_step_decided_fails() {
+        let configuration = SnowflakeConfiguration {
+            quorum_size: 6,
+            sample_size: 10,
+            decision_threshold: 0,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+
+        let mut solver = SnowflakeSolver::with_initial_opinion(
+            configuration,
+            NodeQuery::new(0, "0".to_string()),
+            Opinion::Yes(true),
+        )
+        .unwrap();
+
+        let votes = responses(&[Vote::Yes(true); 10]);
+        solver.step(&votes).unwrap();
+        assert_eq!(solver.decision(), Decision::Decided(Opinion::Yes(true)));
+        assert_eq!(solver.step(&votes), Err(SnowballError::AlreadyDecided));
+    }
+
+    #[test]
+    fn test_invalid_configuration_is_rejected() {
+        let configuration = SnowflakeConfiguration {
+            quorum_size: 0,
+            sample_size: 10,
+            decision_threshold: 1,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+        assert!(matches!(
+            SnowflakeSolver::new(true, configuration, NodeQuery::new(10, "0".to_string())),
+            Err(SnowballError::InvalidConfiguration(_))
+        ));
+        // both opinions could reach a quorum of 5 out of 10 at once
+        let minority = SnowflakeConfiguration {
+            quorum_size: 5,
+            ..configuration
+        };
+        assert!(matches!(
+            SnowflakeSolver::new(true, minority, NodeQuery::new(10, "0".to_string())),
+            Err(SnowballError::InvalidConfiguration(_))
+        ));
+    }
+
+    fn rounds_to_decision<S: ConsensusSolver<bool>>(
+        solver: &mut S,
+        votes: &[QueryResponse<bool>],
+    ) -> usize {
+        let mut rounds = 0;
+        while matches!(solver.decision(), Decision::Undecided(_)) {
+            solver.step(true, votes).unwrap();
+            rounds += 1;
+        }
+        rounds
+    }
+
+    #[test]
+    fn test_consensus_solver() {
+        let configuration = SnowflakeConfiguration {
+            quorum_size: 6,
+            sample_size: 10,
+            decision_threshold: 10,
+            bootstrap: BootstrapPolicy::FirstVote,
+        };
+
+        let mut solver = SnowflakeSolver::with_initial_opinion(
+            configuration,
+            NodeQuery::new(0, "0".to_string()),
+            Opinion::Yes(true),
+        )
+        .unwrap();
+
+        let votes = responses(&[Vote::Yes(true); 10]);
+        assert_eq!(rounds_to_decision(&mut solver, &votes), 11);
+        assert_eq!(ConsensusSolver::vote(&solver), Some(Vote::Yes(true)));
+    }
+}
@@ -0,0 +1,363 @@
+// std
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+// crates
+use claro::{MultiDecision, NodeQuery, NodeWeight, QueryResponse, Vote, VoteQuery};
+use tracing::debug;
+// internal
+use crate::error::SnowmanError;
+use crate::solver::{BlockSolver, SnowmanConfiguration};
+use crate::SNOWMAN_TARGET_TAG;
+
+/// Single block bookkeeping
+struct Block<Id> {
+    parent: Option<Id>,
+    height: u64,
+}
+
+/// Competing children of a block, decided by a single solver
+struct Children<Id> {
+    blocks: Vec<Id>,
+    solver: BlockSolver<Id>,
+}
+
+/// Snowman linear chain consensus
+/// Blocks link to their parent, the children of a block compete at the next height and are
+/// decided by their own solver, following [`SnowmanConfiguration`] semantics. Queries are regular
+/// [`VoteQuery`] rounds over the preferred tip: each responder answers [`Vote::Yes`] with its own
+/// preferred tip, which counts as a vote for every one of its ancestors, or [`Vote::No`] to
+/// abstain. Decided blocks extend the finalized prefix as soon as their parent is finalized.
+pub struct Snowman<Id> {
+    configuration: SnowmanConfiguration,
+    node_query: NodeQuery,
+    blocks: HashMap<Id, Block<Id>>,
+    /// Children competing for each parent block
+    children: HashMap<Id, Children<Id>>,
+    /// Blocks per height, in insertion order
+    heights: Vec<Vec<Id>>,
+    /// Finalized prefix, starting at genesis
+    finalized: Vec<Id>,
+}
+
+impl<Id: Clone + Eq + Hash + Debug> Snowman<Id> {
+    /// Chain starting at the already finalized `genesis` block
+    /// Fails if `configuration` does not [`SnowmanConfiguration::validate`]
+    pub fn new(
+        genesis: Id,
+        configuration: SnowmanConfiguration,
+        mut node_query: NodeQuery,
+    ) -> Result<Self, SnowmanError> {
+        configuration.validate()?;
+        node_query.set_query_size(configuration.query_size());
+        let blocks = HashMap::from([(
+            genesis.clone(),
+            Block {
+                parent: None,
+                height: 0,
+            },
+        )]);
+        Ok(Self {
+            configuration,
+            node_query,
+            blocks,
+            children: HashMap::new(),
+            heights: vec![vec![genesis.clone()]],
+            finalized: vec![genesis],
+        })
+    }
+
+    /// Add block `id` on top of `parent`, the first child of a block is its initial preference
+    /// Inserting an already known block is a no-op.
+    pub fn insert(&mut self, id: Id, parent: Id) -> Result<(), SnowmanError> {
+        if self.blocks.contains_key(&id) {
+            return Ok(());
+        }
+        let height = self.height(&parent)? + 1;
+        match self.children.get_mut(&parent) {
+            Some(children) => {
+                children.solver.insert_value(&id);
+                children.blocks.push(id.clone());
+            }
+            None => {
+                let solver =
+                    BlockSolver::new(self.configuration, self.node_query.clone(), id.clone())?;
+                self.children.insert(
+                    parent.clone(),
+                    Children {
+                        blocks: vec![id.clone()],
+                        solver,
+                    },
+                );
+            }
+        }
+        if self.heights.len() as u64 <= height {
+            self.heights.push(Vec::new());
+        }
+        self.heights[height as usize].push(id.clone());
+        self.blocks.insert(
+            id,
+            Block {
+                parent: Some(parent),
+                height,
+            },
+        );
+        Ok(())
+    }
+
+    /// Compute a single round for every undecided block children from already queried responses
+    pub fn step(&mut self, responses: &[QueryResponse<Id>]) -> Result<(), SnowmanError> {
+        let weighting = self.configuration.weighting();
+        let mut tips: Vec<(&Id, NodeWeight)> = Vec::new();
+        for response in responses {
+            if let Some(Vote::Yes(tip)) = response.as_vote() {
+                if self.blocks.contains_key(tip) {
+                    tips.push((tip, weighting.weight(response)?));
+                }
+            }
+        }
+        let mut rounds = Vec::new();
+        for parent in self.undecided_parents() {
+            let height = self.blocks[parent].height + 1;
+            let parent_votes: Vec<(Id, NodeWeight)> = tips
+                .iter()
+                .filter_map(|&(tip, weight)| Some((self.ancestor_at(tip, height)?, weight)))
+                .filter(|(block, _)| self.blocks[block].parent.as_ref() == Some(parent))
+                .collect();
+            rounds.push((parent.clone(), parent_votes));
+        }
+        for (parent, votes) in rounds {
+            let children = self
+                .children
+                .get_mut(&parent)
+                .expect("Undecided parents have children");
+            children.solver.step(&votes)?;
+            debug!(target: SNOWMAN_TARGET_TAG, parent = ?parent, decision = %children.solver.decision());
+        }
+        self.finalize();
+        Ok(())
+    }
+
+    /// Query the preferred tip in a single round and step on the result
+    pub async fn query_round<Q>(&mut self, query: &mut Q) -> Result<(), SnowmanError>
+    where
+        Q: VoteQuery<Tx = Id>,
+    {
+        let responses = query.query(&self.node_query(), self.preferred_tip()).await;
+        self.step(&responses)
+    }
+
+    /// Node query for the next round
+    /// Sized after the biggest query among the undecided block children.
+    pub fn node_query(&self) -> NodeQuery {
+        let mut node_query = self.node_query.clone();
+        if let Some(query_size) = self
+            .undecided_parents()
+            .into_iter()
+            .map(|parent| self.children[parent].solver.query_size())
+            .max()
+        {
+            node_query.set_query_size(query_size);
+        }
+        node_query
+    }
+
+    /// Tip of the chain made of preferred children, starting at the last finalized block
+    pub fn preferred_tip(&self) -> Id {
+        let mut tip = self.finalized_tip().clone();
+        while let Some(child) = self
+            .children
+            .get(&tip)
+            .and_then(|children| children.solver.decision().into_preference())
+        {
+            tip = child;
+        }
+        tip
+    }
+
+    /// Vote to answer a query with, the preferred tip
+    pub fn vote(&self) -> Vote<Id> {
+        Vote::Yes(self.preferred_tip())
+    }
+
+    /// Finalized prefix, starting at genesis
+    pub fn finalized(&self) -> &[Id] {
+        &self.finalized
+    }
+
+    pub fn finalized_tip(&self) -> &Id {
+        self.finalized.last().expect("Genesis is always finalized")
+    }
+
+    pub fn is_finalized(&self, id: &Id) -> bool {
+        self.blocks
+            .get(id)
+            .and_then(|block| self.finalized.get(block.height as usize))
+            == Some(id)
+    }
+
+    /// Competing blocks at `height`, in insertion order
+    pub fn blocks_at(&self, height: u64) -> &[Id] {
+        self.heights
+            .get(height as usize)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn height(&self, id: &Id) -> Result<u64, SnowmanError> {
+        self.blocks
+            .get(id)
+            .map(|block| block.height)
+            .ok_or_else(|| SnowmanError::UnknownBlock(format!("{id:?}")))
+    }
+
+    /// Decision over the children of `parent`, `None` if it has no children
+    pub fn decision(&self, parent: &Id) -> Option<MultiDecision<Id>> {
+        self.children
+            .get(parent)
+            .map(|children| children.solver.decision())
+    }
+
+    /// Ancestor of `id` at `height`, `id` itself if it is at that height
+    fn ancestor_at(&self, id: &Id, height: u64) -> Option<Id> {
+        let mut current = id;
+        loop {
+            let block = &self.blocks[current];
+            if block.height == height {
+                return Some(current.clone());
+            }
+            if block.height < height {
+                return None;
+            }
+            current = block.parent.as_ref()?;
+        }
+    }
+
+    /// Blocks on top of the finalized prefix, not rejected, whose children are undecided
+    fn undecided_parents(&self) -> Vec<&Id> {
+        let mut parents = Vec::new();
+        let mut next = vec![self.finalized_tip()];
+        while !next.is_empty() {
+            let mut children = Vec::new();
+            for parent in next {
+                let set = match self.children.get(parent) {
+                    Some(set) => set,
+                    None => continue,
+                };
+                match set.solver.decision() {
+                    MultiDecision::Decided(Some(accepted)) => {
+                        children.extend(set.blocks.iter().filter(|block| **block == accepted));
+                    }
+                    _ => {
+                        parents.push(parent);
+                        children.extend(set.blocks.iter());
+                    }
+                }
+            }
+            next = children;
+        }
+        parents
+    }
+
+    /// Extend the finalized prefix with every decided child on top of it
+    fn finalize(&mut self) {
+        while let Some(MultiDecision::Decided(Some(child))) = self.decision(self.finalized_tip()) {
+            debug!(target: SNOWMAN_TARGET_TAG, block = ?child, height = self.finalized.len(), "finalized");
+            self.finalized.push(child);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::chain::Snowman;
+    use crate::error::SnowmanError;
+    use crate::solver::SnowmanConfiguration;
+    use claro::testing::query::*;
+    use claro::BootstrapPolicy;
+    use claro::{ClaroConfiguration, NodeQuery, QueryConfiguration, Vote};
+    use snowball::{SnowballConfiguration, SnowballError};
+
+    fn snowball() -> SnowmanConfiguration {
+        SnowmanConfiguration::Snowball(SnowballConfiguration {
+            quorum_size: 3,
+            sample_size: 5,
+            decision_threshold: 2,
+            bootstrap: BootstrapPolicy::FirstVote,
+        })
+    }
+
+    fn forked_chain(configuration: SnowmanConfiguration) -> Snowman<&'static str> {
+        let mut chain =
+            Snowman::new("genesis", configuration, NodeQuery::new(5, "0".into())).unwrap();
+        chain.insert("a1", "genesis").unwrap();
+        chain.insert("a2", "a1").unwrap();
+        chain.insert("b1", "genesis").unwrap();
+        chain.insert("b2", "b1").unwrap();
+        chain.insert("b3", "b2").unwrap();
+        chain
+    }
+
+    #[test]
+    fn first_child_is_preferred() {
+        let chain = forked_chain(snowball());
+        assert_eq!(chain.preferred_tip(), "a2");
+        assert_eq!(chain.blocks_at(1), &["a1", "b1"]);
+        assert_eq!(chain.height(&"b3"), Ok(3));
+        assert_eq!(chain.finalized(), &["genesis"]);
+    }
+
+    #[test]
+    fn finalizes_voted_branch() {
+        let mut chain = forked_chain(snowball());
+        let votes = responses(&[Vote::Yes("b3"); 5]);
+        chain.step(&votes).unwrap();
+        // a single quorum overtakes the initial preference at every height
+        assert_eq!(chain.preferred_tip(), "b3");
+        while chain.finalized().len() < 4 {
+            chain.step(&votes).unwrap();
+        }
+        assert_eq!(chain.finalized(), &["genesis", "b1", "b2", "b3"]);
+        assert!(chain.is_finalized(&"b2"));
+        assert!(!chain.is_finalized(&"a1"));
+    }
+
+    #[tokio::test]
+    async fn claro_query_rounds() {
+        let mut chain = forked_chain(SnowmanConfiguration::Claro(ClaroConfiguration {
+            query: QueryConfiguration::new(5),
+            ..claro::testing::configuration()
+        }));
+        let mut query = FixedQuery::new(Vote::Yes("a2"));
+        while chain.finalized().len() < 3 {
+            chain.query_round(&mut query).await.unwrap();
+        }
+        assert_eq!(chain.finalized(), &["genesis", "a1", "a2"]);
+        assert_eq!(chain.vote(), Vote::Yes("a2"));
+    }
+
+    #[test]
+    fn invalid_configuration_is_rejected() {
+        let configuration = SnowmanConfiguration::Snowball(SnowballConfiguration {
+            quorum_size: 6,
+            sample_size: 5,
+            decision_threshold: 2,
+            bootstrap: BootstrapPolicy::FirstVote,
+        });
+        assert!(matches!(
+            Snowman::new("genesis", configuration, NodeQuery::new(5, "0".into())),
+            Err(SnowmanError::Snowball(SnowballError::InvalidConfiguration(
+                _
+            )))
+        ));
+    }
+
+    #[test]
+    fn unknown_parent_fails() {
+        let mut chain = forked_chain(snowball());
+        assert_eq!(
+            chain.insert("c1", "c0"),
+            Err(SnowmanError::UnknownBlock("\"c0\"".to_string()))
+        );
+    }
+}
@@ -0,0 +1,42 @@
+// std
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+// crates
+use claro::ClaroError;
+use snowball::SnowballError;
+// internal
+
+/// Snowman chain failures
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnowmanError {
+    /// Block is not part of the chain, holds the block id debug representation
+    UnknownBlock(String),
+    /// Underlying Claro solver failure
+    Claro(ClaroError),
+    /// Underlying Snowball solver failure
+    Snowball(SnowballError),
+}
+
+impl Display for SnowmanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnowmanError::UnknownBlock(id) => write!(f, "unknown block {id}"),
+            SnowmanError::Claro(e) => write!(f, "claro solver: {e}"),
+            SnowmanError::Snowball(e) => write!(f, "snowball solver: {e}"),
+        }
+    }
+}
+
+impl Error for SnowmanError {}
+
+impl From<ClaroError> for SnowmanError {
+    fn from(e: ClaroError) -> Self {
+        SnowmanError::Claro(e)
+    }
+}
+
+impl From<SnowballError> for SnowmanError {
+    fn from(e: SnowballError) -> Self {
+        SnowmanError::Snowball(e)
+    }
+}
@@ -0,0 +1,10 @@
+mod chain;
+mod error;
+mod solver;
+
+pub use crate::chain::Snowman;
+pub use crate::error::SnowmanError;
+pub use crate::solver::SnowmanConfiguration;
+
+/// Snowman logging filtering tag
+pub const SNOWMAN_TARGET_TAG: &str = "SNOWMAN_TARGET";
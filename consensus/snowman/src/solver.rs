@@ -0,0 +1,110 @@
+// std
+use std::fmt::Debug;
+// crates
+use claro::{
+    ClaroConfiguration, EvidenceWeighting, MultiClaroSolver, MultiDecision, NodeQuery, NodeWeight,
+};
+use snowball::{MultiSnowballSolver, SnowballConfiguration};
+// internal
+use crate::error::SnowmanError;
+
+/// Algorithm used to pick a block out of the competing children of a block
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SnowmanConfiguration {
+    Snowball(SnowballConfiguration),
+    Claro(ClaroConfiguration),
+}
+
+impl SnowmanConfiguration {
+    pub fn validate(&self) -> Result<(), SnowmanError> {
+        match self {
+            SnowmanConfiguration::Snowball(snowball) => snowball.validate()?,
+            SnowmanConfiguration::Claro(claro) => claro.validate()?,
+        }
+        Ok(())
+    }
+
+    /// Base size of the queries issued by the chain
+    pub fn query_size(&self) -> usize {
+        match self {
+            SnowmanConfiguration::Snowball(snowball) => snowball.sample_size,
+            SnowmanConfiguration::Claro(claro) => claro.query.query_size,
+        }
+    }
+
+    /// How votes are weighted, snowball always counts them
+    pub fn weighting(&self) -> EvidenceWeighting {
+        match self {
+            SnowmanConfiguration::Snowball(_) => EvidenceWeighting::Count,
+            SnowmanConfiguration::Claro(claro) => claro.weighting,
+        }
+    }
+}
+
+/// Solver dispatcher for the children of a single block
+/// Enum to avoid Boxing the solvers.
+pub enum BlockSolver<Id> {
+    Snowball(MultiSnowballSolver<Id>),
+    Claro(MultiClaroSolver<Id>),
+}
+
+impl<Id: Clone + PartialEq + Debug> BlockSolver<Id> {
+    /// Build a solver preferring `first`, the first child seen
+    pub fn new(
+        configuration: SnowmanConfiguration,
+        node_query: NodeQuery,
+        first: Id,
+    ) -> Result<Self, SnowmanError> {
+        Ok(match configuration {
+            SnowmanConfiguration::Snowball(snowball) => Self::Snowball(MultiSnowballSolver::new(
+                [first.clone()],
+                snowball,
+                node_query,
+                Some(first),
+            )?),
+            SnowmanConfiguration::Claro(claro) => Self::Claro(MultiClaroSolver::new(
+                [first.clone()],
+                claro,
+                node_query,
+                Some(first),
+            )?),
+        })
+    }
+
+    pub fn insert_value(&mut self, value: &Id) {
+        match self {
+            BlockSolver::Snowball(solver) => solver.insert_value(value),
+            BlockSolver::Claro(solver) => solver.insert_value(value),
+        }
+    }
+
+    /// Step on weighted votes, snowball ignores the weights
+    pub fn step(&mut self, votes: &[(Id, NodeWeight)]) -> Result<(), SnowmanError> {
+        match self {
+            BlockSolver::Snowball(solver) => {
+                let votes: Vec<Id> = votes.iter().map(|(id, _)| id.clone()).collect();
+                solver.step(&votes)?
+            }
+            BlockSolver::Claro(solver) => {
+                solver.step_weighted(votes)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn decision(&self) -> MultiDecision<Id> {
+        match self {
+            BlockSolver::Snowball(solver) => solver.decision(),
+            BlockSolver::Claro(solver) => solver.decision(),
+        }
+    }
+
+    pub fn query_size(&self) -> usize {
+        match self {
+            BlockSolver::Snowball(solver) => solver.node_query().query_size(),
+            BlockSolver::Claro(solver) => solver.query_size(),
+        }
+    }
+}
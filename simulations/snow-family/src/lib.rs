@@ -0,0 +1,11 @@
+//! Snow family consensus simulations
+//! Build [`settings::SimulationSettings`], run them through a [`runner::SimulationRunner`] and
+//! collect the [`output_processors::OutData`] records, the `snow-family` binary is a thin cli
+//! on top of this crate.
+pub mod network_behaviour;
+pub mod node;
+pub mod output_processors;
+pub mod runner;
+pub mod settings;
+pub mod sweep;
+pub mod warding;
@@ -1,10 +1,4 @@
 mod app;
-mod network_behaviour;
-mod node;
-mod output_processors;
-mod runner;
-mod settings;
-mod warding;
 
 use crate::app::SimulationApp;
 use clap::Parser;
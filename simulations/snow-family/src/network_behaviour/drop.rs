@@ -12,6 +12,12 @@ pub struct RandomDrop {
     drop_rate: f32,
 }
 
+impl RandomDrop {
+    pub fn new(drop_rate: f32) -> Self {
+        Self { drop_rate }
+    }
+}
+
 impl NetworkBehaviour for RandomDrop {
     fn modify_network_state(&mut self, network_state: &mut [Option<Vote>], rng: &mut SmallRng) {
         let amount: usize =
@@ -1,4 +1,4 @@
-mod drop;
+pub mod drop;
 
 use crate::node::Vote;
 use rand::rngs::SmallRng;
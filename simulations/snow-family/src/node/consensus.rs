@@ -0,0 +1,79 @@
+// std
+use std::fmt::Debug;
+// crates
+use claro::ConsensusSolver;
+use rand::rngs::SmallRng;
+// internal
+use crate::node::{
+    query_network_state, ComputeNode, Decision, NetworkStakes, NetworkState, NoTx, NodeId,
+};
+
+/// Honest consensus node
+/// Wrapper over any [`::claro::ConsensusSolver`]
+pub struct ConsensusNode<S> {
+    solver: S,
+    network_state: NetworkState,
+    stakes: NetworkStakes,
+    node_id: NodeId,
+    rng: SmallRng,
+    /// Query size used in the latest step
+    query_size: usize,
+}
+
+impl<S: ConsensusSolver<NoTx>> ConsensusNode<S> {
+    pub fn new(
+        node_id: usize,
+        solver: S,
+        network_state: NetworkState,
+        stakes: NetworkStakes,
+        rng: SmallRng,
+    ) -> Self {
+        let query_size = solver.node_query().query_size();
+        Self {
+            node_id,
+            query_size,
+            solver,
+            network_state,
+            stakes,
+            rng,
+        }
+    }
+
+    pub fn solver(&self) -> &S {
+        &self.solver
+    }
+
+    pub fn query_size(&self) -> usize {
+        self.query_size
+    }
+}
+
+impl<S> ComputeNode for ConsensusNode<S>
+where
+    S: ConsensusSolver<NoTx>,
+    S::Error: Debug,
+{
+    fn id(&self) -> usize {
+        self.node_id
+    }
+
+    fn step(&mut self) {
+        if matches!(self.solver.decision(), Decision::Undecided(_)) {
+            self.query_size = self.solver.node_query().query_size();
+            let responses = query_network_state(
+                &self.network_state,
+                &self.stakes,
+                self.query_size,
+                self.node_id,
+                &mut self.rng,
+            );
+            self.solver
+                .step(NoTx, &responses)
+                .expect("Step on an undecided solver with validated settings");
+        }
+    }
+
+    fn decision(&self) -> Decision {
+        self.solver.decision()
+    }
+}
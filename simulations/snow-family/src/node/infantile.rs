@@ -56,12 +56,16 @@ impl ComputeNode for InfantileNode {
     }
 
     fn step(&mut self) {
-        let votes = query_network_state(
+        let votes: Vec<Vote> = query_network_state(
             &self.network_state,
+            &[],
             self.query_size,
             self.node_id,
             &mut self.rng,
-        );
+        )
+        .iter()
+        .filter_map(|response| response.as_vote().copied())
+        .collect();
         self.decision = Decision::Undecided(InfantileNode::flip_majority(&votes));
     }
 
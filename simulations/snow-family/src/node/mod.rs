@@ -1,7 +1,7 @@
 // std
 use std::sync::{Arc, RwLock};
 // crates
-use ::claro::ClaroSolver;
+use ::claro::{ClaroSolver, NodeWeight, QueryResponse, ResponseMetadata, ResponseVote};
 use rand::prelude::IteratorRandom;
 use rand::rngs::SmallRng;
 use rand::RngCore;
@@ -10,15 +10,23 @@ use crate::node::claro::ClaroNode;
 use crate::node::infantile::InfantileNode;
 pub use crate::node::omniscient::{MasterOmniscientNode, OmniscientPuppetNode};
 use crate::node::random::RandomNode;
+use crate::node::slush::SlushNode;
 use crate::node::snowball::SnowballNode;
+use crate::node::snowflake::SnowflakeNode;
+use crate::node::snowman::SnowmanNode;
 use crate::output_processors::NodeStateRecord;
-use ::snowball::SnowballSolver;
+use crate::settings::SnowmanSettings;
+use ::snowball::{SlushSolver, SnowballSolver, SnowflakeSolver};
 
-mod claro;
-mod infantile;
-mod omniscient;
-mod random;
-mod snowball;
+pub mod claro;
+pub mod consensus;
+pub mod infantile;
+pub mod omniscient;
+pub mod random;
+pub mod slush;
+pub mod snowball;
+pub mod snowflake;
+pub mod snowman;
 
 /// Consensus experiments consist on just one round, we just care about voting itself not the content
 /// hence we need a Transaction that carries no information.
@@ -39,6 +47,9 @@ pub type NodeId = usize;
 /// Shared hook to the simulation state
 pub type NetworkState = Arc<RwLock<Vec<Option<Vote>>>>;
 
+/// Stake of every node, indexed by node id
+pub type NetworkStakes = Arc<[NodeWeight]>;
+
 /// Node computation abstraction layer
 pub trait ComputeNode {
     fn id(&self) -> usize;
@@ -59,12 +70,15 @@ pub trait ComputeNode {
 }
 
 /// Query the network state for a fixed size skipping self node id
+/// Nodes without a vote are skipped as well, only actual votes are returned. Responses carry the
+/// responder stake, if any, as their weight.
 pub fn query_network_state(
     network_state: &NetworkState,
+    stakes: &[NodeWeight],
     query_size: usize,
     node_id: NodeId,
     rng: &mut impl RngCore,
-) -> Vec<Vote> {
+) -> Vec<QueryResponse<NoTx>> {
     network_state
         .read()
         .unwrap()
@@ -72,7 +86,17 @@ pub fn query_network_state(
         .enumerate()
         .choose_multiple(rng, query_size + 1)
         .into_iter()
-        .filter_map(|(id, vote)| if id != node_id { *vote } else { None })
+        .filter_map(|(id, vote)| match vote {
+            Some(vote) if id != node_id => Some(
+                QueryResponse::new(id.to_string(), ResponseVote::Vote(*vote)).with_metadata(
+                    ResponseMetadata {
+                        weight: stakes.get(id).copied(),
+                        latency: None,
+                    },
+                ),
+            ),
+            _ => None,
+        })
         .take(query_size)
         .collect()
 }
@@ -80,30 +104,85 @@ pub fn query_network_state(
 /// Node dispatcher
 /// Enum to avoid Boxing (Box<dyn ComputeNode>) the nodes.
 pub enum Node {
+    Slush(slush::SlushNode),
+    Snowflake(snowflake::SnowflakeNode),
     Snowball(snowball::SnowballNode),
     Claro(claro::ClaroNode),
+    Snowman(snowman::SnowmanNode),
     Random(random::RandomNode),
     Infantile(infantile::InfantileNode),
     OmniscientPuppet(omniscient::OmniscientPuppetNode),
 }
 
 impl Node {
+    pub fn new_slush(
+        node_id: NodeId,
+        solver: SlushSolver<NoTx>,
+        network_state: NetworkState,
+        stakes: NetworkStakes,
+        rng: SmallRng,
+    ) -> Self {
+        Self::Slush(SlushNode::new(node_id, solver, network_state, stakes, rng))
+    }
+
+    pub fn new_snowflake(
+        node_id: NodeId,
+        solver: SnowflakeSolver<NoTx>,
+        network_state: NetworkState,
+        stakes: NetworkStakes,
+        rng: SmallRng,
+    ) -> Self {
+        Self::Snowflake(SnowflakeNode::new(
+            node_id,
+            solver,
+            network_state,
+            stakes,
+            rng,
+        ))
+    }
+
     pub fn new_snowball(
         node_id: NodeId,
         solver: SnowballSolver<NoTx>,
         network_state: NetworkState,
+        stakes: NetworkStakes,
         rng: SmallRng,
     ) -> Self {
-        Self::Snowball(SnowballNode::new(node_id, solver, network_state, rng))
+        Self::Snowball(SnowballNode::new(
+            node_id,
+            solver,
+            network_state,
+            stakes,
+            rng,
+        ))
     }
 
     pub fn new_claro(
         node_id: NodeId,
         solver: ClaroSolver<NoTx>,
         network_state: NetworkState,
+        stakes: NetworkStakes,
         seed: SmallRng,
     ) -> Self {
-        Self::Claro(ClaroNode::new(node_id, solver, network_state, seed))
+        Self::Claro(ClaroNode::new(node_id, solver, network_state, stakes, seed))
+    }
+
+    pub fn new_snowman(
+        node_id: NodeId,
+        settings: SnowmanSettings,
+        opinion: Opinion,
+        network_state: NetworkState,
+        stakes: NetworkStakes,
+        rng: SmallRng,
+    ) -> Self {
+        Self::Snowman(SnowmanNode::new(
+            node_id,
+            settings,
+            opinion,
+            network_state,
+            stakes,
+            rng,
+        ))
     }
 
     pub fn new_random(node_id: NodeId) -> Self {
@@ -126,8 +205,11 @@ impl Node {
     /// Get `ComputeNode` inner mut reference
     pub fn inner_node_mut(&mut self) -> &mut dyn ComputeNode {
         let node: &mut dyn ComputeNode = match self {
+            Node::Slush(node) => node,
+            Node::Snowflake(node) => node,
             Node::Snowball(node) => node,
             Node::Claro(node) => node,
+            Node::Snowman(node) => node,
             Node::Random(node) => node,
             Node::Infantile(node) => node,
             Node::OmniscientPuppet(node) => node,
@@ -138,8 +220,11 @@ impl Node {
     /// Get `ComputeNode` inner reference
     pub fn inner_node(&self) -> &dyn ComputeNode {
         let node: &dyn ComputeNode = match self {
+            Node::Slush(node) => node,
+            Node::Snowflake(node) => node,
             Node::Snowball(node) => node,
             Node::Claro(node) => node,
+            Node::Snowman(node) => node,
             Node::Random(node) => node,
             Node::Infantile(node) => node,
             Node::OmniscientPuppet(node) => node,
@@ -149,8 +234,11 @@ impl Node {
 
     pub fn serialized_state(&self) -> &dyn NodeStateRecord {
         match self {
+            Node::Slush(node) => node,
+            Node::Snowflake(node) => node,
             Node::Snowball(node) => node,
             Node::Claro(node) => node,
+            Node::Snowman(node) => node,
             Node::Random(node) => node,
             Node::Infantile(node) => node,
             Node::OmniscientPuppet(node) => node,
@@ -159,8 +247,11 @@ impl Node {
 
     pub fn type_as_string(&self) -> String {
         match self {
+            Node::Slush(_) => "slush",
+            Node::Snowflake(_) => "snowflake",
             Node::Snowball(_) => "snowball",
             Node::Claro(_) => "claro",
+            Node::Snowman(_) => "snowman",
             Node::Random(_) => "random",
             Node::Infantile(_) => "infantile",
             Node::OmniscientPuppet(_) => "omniscient",
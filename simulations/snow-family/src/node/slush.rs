@@ -0,0 +1,13 @@
+// std
+// crates
+// internal
+use crate::node::consensus::ConsensusNode;
+use crate::node::NoTx;
+use crate::output_processors::NodeStateRecord;
+use snowball::SlushSolver;
+
+/// Slush consensus node
+/// Wrapper over [`::snowball::SlushSolver`]
+pub type SlushNode = ConsensusNode<SlushSolver<NoTx>>;
+
+impl NodeStateRecord for SlushNode {}
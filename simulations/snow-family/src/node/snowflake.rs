@@ -0,0 +1,19 @@
+// std
+// crates
+// internal
+use crate::node::consensus::ConsensusNode;
+use crate::node::NoTx;
+use crate::output_processors::{NodeStateRecord, OutNodeState};
+use snowball::SnowflakeSolver;
+
+/// Snowflake consensus node
+/// Wrapper over [`::snowball::SnowflakeSolver`]
+pub type SnowflakeNode = ConsensusNode<SnowflakeSolver<NoTx>>;
+
+impl NodeStateRecord for SnowflakeNode {
+    fn get_state_record(&self) -> OutNodeState {
+        OutNodeState::Snowflake {
+            consecutive_success: self.solver().consecutive_success(),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+// std
+// crates
+use rand::rngs::SmallRng;
+// internal
+use crate::node::{
+    query_network_state, ComputeNode, Decision, NetworkStakes, NetworkState, NoTx, NodeId, Opinion,
+};
+use crate::output_processors::{NodeStateRecord, OutNodeState};
+use crate::settings::SnowmanSettings;
+use claro::{NodeQuery, QueryResponse, ResponseVote};
+use snowman::Snowman;
+
+/// Block of the simulated chain
+/// Two forks compete from genesis on, `true` being the fork voted as yes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ForkBlock {
+    height: u64,
+    /// `None` for genesis
+    fork: Option<bool>,
+}
+
+impl ForkBlock {
+    const GENESIS: ForkBlock = ForkBlock {
+        height: 0,
+        fork: None,
+    };
+}
+
+/// Snowman consensus node
+/// Wrapper over [`::snowman::Snowman`], building a two forks chain of
+/// [`SnowmanSettings::chain_length`] blocks each. Yes and no votes stand for the tip of each fork.
+pub struct SnowmanNode {
+    chain: Snowman<ForkBlock>,
+    chain_length: u64,
+    network_state: NetworkState,
+    stakes: NetworkStakes,
+    node_id: NodeId,
+    rng: SmallRng,
+    /// Query size used in the latest step
+    query_size: usize,
+}
+
+impl SnowmanNode {
+    pub fn new(
+        node_id: NodeId,
+        settings: SnowmanSettings,
+        opinion: Opinion,
+        network_state: NetworkState,
+        stakes: NetworkStakes,
+        rng: SmallRng,
+    ) -> Self {
+        let chain = Snowman::new(
+            ForkBlock::GENESIS,
+            settings.solver,
+            NodeQuery::new(settings.solver.query_size(), node_id.to_string()),
+        )
+        .expect("Snowman settings are checked before running");
+        let mut node = Self {
+            query_size: chain.node_query().query_size(),
+            chain,
+            chain_length: settings.chain_length,
+            network_state,
+            stakes,
+            node_id,
+            rng,
+        };
+        if let Some(fork) = opinion.preference() {
+            node.insert_forks(fork);
+        }
+        node
+    }
+
+    /// Build both forks, `preferred` first so it becomes the initial preference
+    fn insert_forks(&mut self, preferred: bool) {
+        for fork in [preferred, !preferred] {
+            let mut parent = ForkBlock::GENESIS;
+            for height in 1..=self.chain_length {
+                let block = ForkBlock {
+                    height,
+                    fork: Some(fork),
+                };
+                self.chain
+                    .insert(block, parent)
+                    .expect("Parent block was just inserted");
+                parent = block;
+            }
+        }
+    }
+
+    fn tip(&self, fork: bool) -> ForkBlock {
+        ForkBlock {
+            height: self.chain_length,
+            fork: Some(fork),
+        }
+    }
+
+    fn is_decided(&self) -> bool {
+        self.chain.finalized().len() as u64 > self.chain_length
+    }
+
+    pub fn finalized_height(&self) -> u64 {
+        self.chain.finalized().len() as u64 - 1
+    }
+}
+
+impl ComputeNode for SnowmanNode {
+    fn id(&self) -> usize {
+        self.node_id
+    }
+
+    fn step(&mut self) {
+        if self.is_decided() {
+            return;
+        }
+        self.query_size = self.chain.node_query().query_size();
+        let responses = query_network_state(
+            &self.network_state,
+            &self.stakes,
+            self.query_size,
+            self.node_id,
+            &mut self.rng,
+        );
+        // nodes without an opinion adopt the fork of the first vote they see
+        if self.chain.blocks_at(1).is_empty() {
+            match responses.iter().find_map(QueryResponse::as_vote) {
+                Some(vote) => self.insert_forks(vote.preference()),
+                None => return,
+            }
+        }
+        let responses: Vec<_> = responses
+            .into_iter()
+            .filter_map(|response| {
+                let tip = self.tip(response.as_vote()?.preference());
+                Some(
+                    QueryResponse::new(
+                        response.node_id,
+                        ResponseVote::Vote(::claro::Vote::Yes(tip)),
+                    )
+                    .with_metadata(response.metadata),
+                )
+            })
+            .collect();
+        self.chain
+            .step(&responses)
+            .expect("Step on an undecided chain with validated settings");
+    }
+
+    fn decision(&self) -> Decision {
+        let fork = self.chain.preferred_tip().fork;
+        let opinion = Opinion::from_preference(NoTx, fork);
+        if self.is_decided() {
+            Decision::Decided(opinion)
+        } else {
+            Decision::Undecided(opinion)
+        }
+    }
+}
+
+impl NodeStateRecord for SnowmanNode {
+    fn get_state_record(&self) -> OutNodeState {
+        OutNodeState::Snowman {
+            finalized_height: self.finalized_height(),
+            query_size: self.query_size as u64,
+        }
+    }
+}
@@ -1,19 +1,513 @@
+// std
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::path::Path;
+use std::str::FromStr;
+// crates
+use polars::io::SerWriter;
+use polars::prelude::{DataFrame, JsonFormat, NamedFrom, Series};
 use serde::Serialize;
+// internal
+use crate::settings::ConsensusSettings;
+use claro::{EvidenceWeighting, RoundFormula};
 
-pub type SerializedNodeState = serde_json::Value;
+mod writer;
 
-#[derive(Serialize)]
+pub use writer::OutDataWriter;
+
+/// Consensus state of a single node, unnested as one output column per field
+/// Fields shared among node types share their column, the other node types leave it null.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum OutNodeState {
+    /// Nodes without any consensus state to record
+    Stateless,
+    Snowflake {
+        consecutive_success: u64,
+    },
+    Snowball {
+        consecutive_success: u64,
+        yes_confidence: u64,
+        no_confidence: u64,
+    },
+    Claro {
+        evidence: f64,
+        evidence_accumulated: f64,
+        confidence: f64,
+        query_size: u64,
+    },
+    Snowman {
+        finalized_height: u64,
+        query_size: u64,
+    },
+}
+
+/// Node state columns written by a simulation
+/// Picked once per run out of its [`ConsensusSettings`], so that every output batch shares the
+/// same schema whatever the node types present in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutStateSchema {
+    /// No state column, as for Slush nodes
+    Stateless,
+    Snowflake,
+    Snowball,
+    Claro {
+        /// Evidence and confidence are whole vote counts, written as integer columns
+        integer_counters: bool,
+    },
+    Snowman,
+}
+
+impl From<&ConsensusSettings> for OutStateSchema {
+    fn from(settings: &ConsensusSettings) -> Self {
+        match settings {
+            ConsensusSettings::Slush(_) => Self::Stateless,
+            ConsensusSettings::SnowFlake(_) => Self::Snowflake,
+            ConsensusSettings::SnowBall(_) => Self::Snowball,
+            // decayed evidence is fractional even when counting votes
+            ConsensusSettings::Claro(claro) => Self::Claro {
+                integer_counters: matches!(claro.weighting, EvidenceWeighting::Count)
+                    && !matches!(claro.formula, RoundFormula::Decay(_)),
+            },
+            ConsensusSettings::Snowman(_) => Self::Snowman,
+        }
+    }
+}
+
+/// Constant output column, name and value
+pub type OutTag = (String, serde_json::Value);
+
+/// Single node state record, taken once per simulation step
+#[derive(Debug, Clone, Serialize)]
 pub struct OutData {
     pub id: u64,
     pub iteration: u64,
     pub round: u64,
     pub vote: u8,
     pub _type: String,
-    pub state: SerializedNodeState,
+    pub state: OutNodeState,
+}
+
+/// Destination of the simulation records
+pub trait OutDataSink {
+    /// Take the records of every node for a single simulation step
+    fn push_step(&mut self, records: Vec<OutData>);
+}
+
+impl OutDataSink for Vec<OutData> {
+    fn push_step(&mut self, mut records: Vec<OutData>) {
+        self.append(&mut records);
+    }
 }
 
 pub trait NodeStateRecord {
-    fn get_serialized_state_record(&self) -> SerializedNodeState {
-        SerializedNodeState::Null
+    fn get_state_record(&self) -> OutNodeState {
+        OutNodeState::Stateless
+    }
+}
+
+/// Output format selector enum
+#[derive(Debug, Clone, Copy, Default)]
+pub enum OutputFormat {
+    Json,
+    /// Newline delimited json, one record per line
+    Ndjson,
+    Csv,
+    #[default]
+    Parquet,
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let tag = match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Parquet => "parquet",
+        };
+        write!(f, "{}", tag)
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = std::io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "ndjson" => Ok(Self::Ndjson),
+            "csv" => Ok(Self::Csv),
+            "parquet" => Ok(Self::Parquet),
+            tag => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Invalid {} tag, only [json, ndjson, csv, parquet] are supported",
+                    tag
+                ),
+            )),
+        }
+    }
+}
+
+/// Nullable node state columns, in output order
+#[derive(Default)]
+struct StateColumns {
+    consecutive_success: Vec<Option<i64>>,
+    yes_confidence: Vec<Option<i64>>,
+    no_confidence: Vec<Option<i64>>,
+    evidence: Vec<Option<f64>>,
+    evidence_accumulated: Vec<Option<f64>>,
+    confidence: Vec<Option<f64>>,
+    finalized_height: Vec<Option<i64>>,
+    query_size: Vec<Option<i64>>,
+}
+
+impl StateColumns {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            consecutive_success: Vec::with_capacity(capacity),
+            yes_confidence: Vec::with_capacity(capacity),
+            no_confidence: Vec::with_capacity(capacity),
+            evidence: Vec::with_capacity(capacity),
+            evidence_accumulated: Vec::with_capacity(capacity),
+            confidence: Vec::with_capacity(capacity),
+            finalized_height: Vec::with_capacity(capacity),
+            query_size: Vec::with_capacity(capacity),
+        }
+    }
+
+    fn push(&mut self, state: &OutNodeState) {
+        let (mut consecutive_success, mut yes_confidence, mut no_confidence) = (None, None, None);
+        let (mut evidence, mut evidence_accumulated, mut confidence) = (None, None, None);
+        let (mut finalized_height, mut query_size) = (None, None);
+        match *state {
+            OutNodeState::Stateless => {}
+            OutNodeState::Snowflake {
+                consecutive_success: success,
+            } => consecutive_success = Some(success as i64),
+            OutNodeState::Snowball {
+                consecutive_success: success,
+                yes_confidence: yes,
+                no_confidence: no,
+            } => {
+                consecutive_success = Some(success as i64);
+                yes_confidence = Some(yes as i64);
+                no_confidence = Some(no as i64);
+            }
+            OutNodeState::Claro {
+                evidence: value_evidence,
+                evidence_accumulated: accumulated,
+                confidence: value_confidence,
+                query_size: size,
+            } => {
+                evidence = Some(value_evidence);
+                evidence_accumulated = Some(accumulated);
+                confidence = Some(value_confidence);
+                query_size = Some(size as i64);
+            }
+            OutNodeState::Snowman {
+                finalized_height: height,
+                query_size: size,
+            } => {
+                finalized_height = Some(height as i64);
+                query_size = Some(size as i64);
+            }
+        }
+        self.consecutive_success.push(consecutive_success);
+        self.yes_confidence.push(yes_confidence);
+        self.no_confidence.push(no_confidence);
+        self.evidence.push(evidence);
+        self.evidence_accumulated.push(evidence_accumulated);
+        self.confidence.push(confidence);
+        self.finalized_height.push(finalized_height);
+        self.query_size.push(query_size);
+    }
+
+    /// Columns of `schema`, whatever the node types present in the records
+    fn into_series(self, schema: OutStateSchema) -> Vec<Series> {
+        match schema {
+            OutStateSchema::Stateless => vec![],
+            OutStateSchema::Snowflake => {
+                vec![Series::new("consecutive_success", self.consecutive_success)]
+            }
+            OutStateSchema::Snowball => vec![
+                Series::new("consecutive_success", self.consecutive_success),
+                Series::new("yes_confidence", self.yes_confidence),
+                Series::new("no_confidence", self.no_confidence),
+            ],
+            OutStateSchema::Claro { integer_counters } => {
+                let counter = |name: &str, values: Vec<Option<f64>>| {
+                    if integer_counters {
+                        let values: Vec<Option<i64>> = values
+                            .into_iter()
+                            .map(|value| value.map(|value| value as i64))
+                            .collect();
+                        Series::new(name, values)
+                    } else {
+                        Series::new(name, values)
+                    }
+                };
+                vec![
+                    counter("evidence", self.evidence),
+                    counter("evidence_accumulated", self.evidence_accumulated),
+                    counter("confidence", self.confidence),
+                    Series::new("query_size", self.query_size),
+                ]
+            }
+            OutStateSchema::Snowman => vec![
+                Series::new("finalized_height", self.finalized_height),
+                Series::new("query_size", self.query_size),
+            ],
+        }
+    }
+}
+
+/// Build a dataframe out of the simulation records, node states unnested as `schema` columns
+pub fn out_data_to_dataframe(out_data: Vec<OutData>, schema: OutStateSchema) -> DataFrame {
+    let rows = out_data.len();
+    let mut id = Vec::with_capacity(rows);
+    let mut iteration = Vec::with_capacity(rows);
+    let mut round = Vec::with_capacity(rows);
+    let mut vote = Vec::with_capacity(rows);
+    let mut _type = Vec::with_capacity(rows);
+    let mut state = StateColumns::with_capacity(rows);
+    for record in &out_data {
+        id.push(record.id as i64);
+        iteration.push(record.iteration as i64);
+        round.push(record.round as i64);
+        vote.push(record.vote as i64);
+        _type.push(record._type.as_str());
+        state.push(&record.state);
+    }
+    let mut series = vec![
+        Series::new("id", id),
+        Series::new("iteration", iteration),
+        Series::new("round", round),
+        Series::new("vote", vote),
+        Series::new("_type", _type),
+    ];
+    series.extend(state.into_series(schema));
+    DataFrame::new(series).expect("Columns should all have the records length")
+}
+
+/// Constant `height` long column out of a tag value
+/// Numbers and strings keep their type, any other value is written as json text.
+fn tag_series(name: &str, value: &serde_json::Value, height: usize) -> Series {
+    match value {
+        serde_json::Value::Number(number) => match number.as_i64() {
+            Some(number) => Series::new(name, vec![number; height]),
+            None => Series::new(name, vec![number.as_f64().unwrap_or(f64::NAN); height]),
+        },
+        serde_json::Value::String(value) => Series::new(name, vec![value.as_str(); height]),
+        value => Series::new(name, vec![value.to_string(); height]),
+    }
+}
+
+fn dump_dataframe_to_json(data: &mut DataFrame, out_path: &Path) -> Result<(), Box<dyn Error>> {
+    let out_path = out_path.with_extension("json");
+    let f = File::create(out_path)?;
+    let mut writer = polars::prelude::JsonWriter::new(f);
+    writer
+        .finish(data)
+        .map_err(|e| Box::new(e) as Box<dyn Error>)
+}
+
+fn dump_dataframe_to_ndjson(data: &mut DataFrame, out_path: &Path) -> Result<(), Box<dyn Error>> {
+    let out_path = out_path.with_extension("ndjson");
+    let f = File::create(out_path)?;
+    let mut writer = polars::prelude::JsonWriter::new(f).with_json_format(JsonFormat::JsonLines);
+    writer
+        .finish(data)
+        .map_err(|e| Box::new(e) as Box<dyn Error>)
+}
+
+fn dump_dataframe_to_csv(data: &mut DataFrame, out_path: &Path) -> Result<(), Box<dyn Error>> {
+    let out_path = out_path.with_extension("csv");
+    let f = File::create(out_path)?;
+    let mut writer = polars::prelude::CsvWriter::new(f);
+    writer
+        .finish(data)
+        .map_err(|e| Box::new(e) as Box<dyn Error>)
+}
+
+fn dump_dataframe_to_parquet(data: &mut DataFrame, out_path: &Path) -> Result<(), Box<dyn Error>> {
+    let out_path = out_path.with_extension("parquet");
+    let f = File::create(out_path)?;
+    let writer = polars::prelude::ParquetWriter::new(f);
+    writer
+        .finish(data)
+        .map_err(|e| Box::new(e) as Box<dyn Error>)
+}
+
+/// Write `data` to `out_path`, its extension replaced by the one of `output_format`
+pub fn dump_dataframe_to(
+    output_format: OutputFormat,
+    data: &mut DataFrame,
+    out_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    match output_format {
+        OutputFormat::Json => dump_dataframe_to_json(data, out_path),
+        OutputFormat::Ndjson => dump_dataframe_to_ndjson(data, out_path),
+        OutputFormat::Csv => dump_dataframe_to_csv(data, out_path),
+        OutputFormat::Parquet => dump_dataframe_to_parquet(data, out_path),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::output_processors::{
+        out_data_to_dataframe, OutData, OutNodeState, OutStateSchema, StateColumns,
+    };
+    use crate::settings::ConsensusSettings;
+    use polars::prelude::DataType;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn state_columns_from_mixed_nodes() {
+        let mut columns = StateColumns::default();
+        columns.push(&OutNodeState::Claro {
+            evidence: 2.0,
+            evidence_accumulated: 4.0,
+            confidence: 4.0,
+            query_size: 10,
+        });
+        columns.push(&OutNodeState::Stateless);
+        assert_eq!(columns.evidence, vec![Some(2.0), None]);
+        assert_eq!(columns.query_size, vec![Some(10), None]);
+        assert_eq!(columns.consecutive_success, vec![None, None]);
+        assert_eq!(columns.finalized_height, vec![None, None]);
+    }
+
+    /// State column names and types of a run under `settings`, with a byzantine node next to one
+    /// recording `state`
+    fn state_schema(settings: Value, state: OutNodeState) -> Vec<(String, DataType)> {
+        let settings: ConsensusSettings = serde_json::from_value(settings).unwrap();
+        let records = [state, OutNodeState::Stateless]
+            .into_iter()
+            .map(|state| OutData {
+                id: 0,
+                iteration: 0,
+                round: 0,
+                vote: 1,
+                _type: "Honest".to_string(),
+                state,
+            })
+            .collect();
+        let dataframe = out_data_to_dataframe(records, OutStateSchema::from(&settings));
+        dataframe
+            .get_columns()
+            .iter()
+            .skip(5)
+            .map(|series| (series.name().to_string(), series.dtype().clone()))
+            .collect()
+    }
+
+    fn columns(columns: &[(&str, DataType)]) -> Vec<(String, DataType)> {
+        columns
+            .iter()
+            .map(|(name, dtype)| (name.to_string(), dtype.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn state_columns_follow_consensus_settings() {
+        let snowball = json!({ "quorum_size": 14, "sample_size": 20, "decision_threshold": 20 });
+        let claro = json!({
+            "evidence_alpha": 0.8,
+            "evidence_alpha_2": 0.5,
+            "confidence_beta": 0.8,
+            "look_ahead": 20,
+            "query": {
+                "query_size": 30,
+                "initial_query_size": 30,
+                "query_multiplier": 2,
+                "max_multiplier": 4
+            }
+        });
+        let claro_state = OutNodeState::Claro {
+            evidence: 2.0,
+            evidence_accumulated: 4.0,
+            confidence: 4.0,
+            query_size: 30,
+        };
+
+        let slush = json!({ "slush": { "quorum_size": 14, "sample_size": 20, "rounds": 20 } });
+        assert_eq!(state_schema(slush, OutNodeState::Stateless), vec![]);
+        assert_eq!(
+            state_schema(
+                json!({ "snow_flake": snowball }),
+                OutNodeState::Snowflake {
+                    consecutive_success: 1
+                }
+            ),
+            columns(&[("consecutive_success", DataType::Int64)])
+        );
+        assert_eq!(
+            state_schema(
+                json!({ "snow_ball": snowball }),
+                OutNodeState::Snowball {
+                    consecutive_success: 1,
+                    yes_confidence: 1,
+                    no_confidence: 0,
+                }
+            ),
+            columns(&[
+                ("consecutive_success", DataType::Int64),
+                ("yes_confidence", DataType::Int64),
+                ("no_confidence", DataType::Int64),
+            ])
+        );
+        let claro_columns = |counter: DataType| {
+            columns(&[
+                ("evidence", counter.clone()),
+                ("evidence_accumulated", counter.clone()),
+                ("confidence", counter),
+                ("query_size", DataType::Int64),
+            ])
+        };
+        assert_eq!(
+            state_schema(json!({ "claro": claro }), claro_state),
+            claro_columns(DataType::Int64)
+        );
+        let mut staked = claro.clone();
+        staked["weighting"] = json!("stake");
+        assert_eq!(
+            state_schema(json!({ "claro": staked }), claro_state),
+            claro_columns(DataType::Float64)
+        );
+        let mut decayed = claro.clone();
+        decayed["formula"] = json!({ "decay": { "decay": 0.5 } });
+        assert_eq!(
+            state_schema(json!({ "claro": decayed }), claro_state),
+            claro_columns(DataType::Float64)
+        );
+        assert_eq!(
+            state_schema(
+                json!({ "snowman": { "chain_length": 10, "solver": { "snowball": snowball } } }),
+                OutNodeState::Snowman {
+                    finalized_height: 1,
+                    query_size: 20,
+                }
+            ),
+            columns(&[
+                ("finalized_height", DataType::Int64),
+                ("query_size", DataType::Int64),
+            ])
+        );
+    }
+
+    #[test]
+    fn state_serializes_unnested() {
+        let state = OutNodeState::Snowflake {
+            consecutive_success: 3,
+        };
+        assert_eq!(
+            serde_json::to_value(state).unwrap(),
+            serde_json::json!({ "consecutive_success": 3 })
+        );
+        assert!(serde_json::to_value(OutNodeState::Stateless)
+            .unwrap()
+            .is_null());
     }
 }
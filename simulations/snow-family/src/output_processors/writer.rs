@@ -0,0 +1,205 @@
+// std
+use std::error::Error;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+// crates
+use polars::io::SerWriter;
+use polars::prelude::{CsvWriter, DataFrame, JsonFormat, JsonWriter, ParquetWriter, Series};
+// internal
+use crate::output_processors::{
+    dump_dataframe_to, out_data_to_dataframe, tag_series, OutData, OutDataSink, OutStateSchema,
+    OutTag, OutputFormat,
+};
+
+/// Streams the simulation records to `out_path` in batches, as the simulation runs
+/// Records are buffered until a step takes them over `batch_size` rows, then written out, so
+/// memory use does not grow with the run length. Csv and ndjson batches are appended to a single
+/// file. Parquet files cannot be appended to, so each batch goes to its own numbered part file
+/// (`out.00000.parquet`, `out.00001.parquet`...), however short the run.
+/// Json output is a single array: it is not streamed, every record is buffered for the whole run
+/// regardless of `batch_size` and written on [`Self::finish`], so its memory use is unbounded.
+pub struct OutDataWriter {
+    format: OutputFormat,
+    out_path: PathBuf,
+    batch_size: usize,
+    /// Node state columns, the same for every batch
+    schema: OutStateSchema,
+    buffer: Vec<OutData>,
+    /// Constant columns appended to every record
+    tags: Vec<OutTag>,
+    /// Single output file, for the formats that can be appended to
+    file: Option<File>,
+    batches: usize,
+    /// First write error, the remaining records are dropped once set
+    error: Option<Box<dyn Error>>,
+}
+
+impl OutDataWriter {
+    pub fn new(
+        format: OutputFormat,
+        out_path: &Path,
+        batch_size: usize,
+        schema: OutStateSchema,
+    ) -> Result<Self, Box<dyn Error>> {
+        let file = match format {
+            OutputFormat::Csv | OutputFormat::Ndjson => {
+                Some(File::create(out_path.with_extension(format.to_string()))?)
+            }
+            OutputFormat::Json | OutputFormat::Parquet => None,
+        };
+        Ok(Self {
+            format,
+            out_path: out_path.to_path_buf(),
+            batch_size,
+            schema,
+            buffer: Vec::new(),
+            tags: Vec::new(),
+            file,
+            batches: 0,
+            error: None,
+        })
+    }
+
+    /// Append a constant `name` column holding `value` to every record
+    pub fn with_tag(mut self, name: String, value: serde_json::Value) -> Self {
+        self.tags.push((name, value));
+        self
+    }
+
+    /// Write the buffered records out, reporting the first error found while streaming if any
+    pub fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.flush()
+    }
+
+    /// Dataframe out of the buffered records, tags included
+    fn take_dataframe(&mut self) -> Result<DataFrame, Box<dyn Error>> {
+        let mut dataframe = out_data_to_dataframe(std::mem::take(&mut self.buffer), self.schema);
+        let height = dataframe.height();
+        let tags: Vec<Series> = self
+            .tags
+            .iter()
+            .map(|(name, value)| tag_series(name, value, height))
+            .collect();
+        dataframe.hstack_mut(&tags)?;
+        Ok(dataframe)
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let mut dataframe = self.take_dataframe()?;
+        match (self.format, self.file.as_mut()) {
+            (OutputFormat::Csv, Some(file)) => CsvWriter::new(file)
+                .has_header(self.batches == 0)
+                .finish(&mut dataframe)?,
+            (OutputFormat::Ndjson, Some(file)) => JsonWriter::new(file)
+                .with_json_format(JsonFormat::JsonLines)
+                .finish(&mut dataframe)?,
+            (OutputFormat::Parquet, _) => {
+                let part = format!("{:05}.{}", self.batches, self.format);
+                let file = File::create(self.out_path.with_extension(part))?;
+                ParquetWriter::new(file).finish(&mut dataframe)?
+            }
+            (format, _) => dump_dataframe_to(format, &mut dataframe, &self.out_path)?,
+        }
+        self.batches += 1;
+        Ok(())
+    }
+}
+
+impl OutDataSink for OutDataWriter {
+    fn push_step(&mut self, mut records: Vec<OutData>) {
+        if self.error.is_some() {
+            return;
+        }
+        self.buffer.append(&mut records);
+        if matches!(self.format, OutputFormat::Json) || self.buffer.len() < self.batch_size {
+            return;
+        }
+        if let Err(e) = self.flush() {
+            self.error = Some(e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::output_processors::{
+        OutData, OutDataSink, OutDataWriter, OutNodeState, OutStateSchema, OutputFormat,
+    };
+    use std::path::PathBuf;
+
+    fn step(iteration: u64) -> Vec<OutData> {
+        (0..4)
+            .map(|id| OutData {
+                id,
+                iteration,
+                round: iteration,
+                vote: 1,
+                _type: "Random".to_string(),
+                state: OutNodeState::Stateless,
+            })
+            .collect()
+    }
+
+    fn out_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("snow-family-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn parquet_batches_to_parts() {
+        let dir = out_dir("parquet");
+        let mut writer = OutDataWriter::new(
+            OutputFormat::Parquet,
+            &dir.join("out"),
+            6,
+            OutStateSchema::Stateless,
+        )
+        .unwrap();
+        for iteration in 0..3 {
+            writer.push_step(step(iteration));
+        }
+        writer.finish().unwrap();
+        assert!(dir.join("out.00000.parquet").exists());
+        assert!(dir.join("out.00001.parquet").exists());
+        assert!(!dir.join("out.parquet").exists());
+        // part files are written even if the run fits in a single batch
+        let mut writer = OutDataWriter::new(
+            OutputFormat::Parquet,
+            &dir.join("short"),
+            100,
+            OutStateSchema::Stateless,
+        )
+        .unwrap();
+        writer.push_step(step(0));
+        writer.finish().unwrap();
+        assert!(dir.join("short.00000.parquet").exists());
+        assert!(!dir.join("short.parquet").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn json_to_single_file() {
+        let dir = out_dir("json");
+        let mut writer = OutDataWriter::new(
+            OutputFormat::Json,
+            &dir.join("out"),
+            6,
+            OutStateSchema::Stateless,
+        )
+        .unwrap();
+        for iteration in 0..3 {
+            writer.push_step(step(iteration));
+        }
+        writer.finish().unwrap();
+        assert!(dir.join("out.json").exists());
+        assert!(!dir.join("out.00000.json").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,5 +1,5 @@
 use crate::node::{ComputeNode, Vote};
-use crate::output_processors::OutData;
+use crate::output_processors::OutDataSink;
 use crate::runner::SimulationRunner;
 use crate::warding::SimulationState;
 use rand::prelude::SliceRandom;
@@ -10,7 +10,7 @@ use std::sync::Arc;
 pub fn simulate(
     runner: &mut SimulationRunner,
     chunk_size: usize,
-    mut out_data: Option<&mut Vec<OutData>>,
+    mut out_data: Option<&mut dyn OutDataSink>,
 ) {
     let mut node_ids: Vec<usize> = (0..runner
         .nodes
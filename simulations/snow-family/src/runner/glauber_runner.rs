@@ -1,5 +1,5 @@
 use crate::node::{ComputeNode, Node, NodeId};
-use crate::output_processors::OutData;
+use crate::output_processors::OutDataSink;
 use crate::runner::SimulationRunner;
 use crate::warding::SimulationState;
 use rand::prelude::IteratorRandom;
@@ -11,7 +11,7 @@ pub fn simulate(
     runner: &mut SimulationRunner,
     update_rate: usize,
     maximum_iterations: usize,
-    mut out_data: Option<&mut Vec<OutData>>,
+    mut out_data: Option<&mut dyn OutDataSink>,
 ) {
     let mut simulation_state = SimulationState {
         network_state: Arc::clone(&runner.network_state),
@@ -11,7 +11,7 @@
 //! To solve this we can use a concept of layered *glauber* executions.
 //! The algorithm roughly works as follows:
 //!
-//! ```
+//! ```text
 //! nodes <- [nodes]
 //! layers <- [[nodes_ids], [], ...]
 //! while nodes_to_compute(layers):
@@ -37,7 +37,7 @@ use rand::prelude::{IteratorRandom, SliceRandom};
 use rand::rngs::SmallRng;
 // internal
 use crate::node::{ComputeNode, Node, NodeId};
-use crate::output_processors::OutData;
+use crate::output_processors::OutDataSink;
 use crate::runner::SimulationRunner;
 use crate::warding::SimulationState;
 
@@ -45,10 +45,9 @@ pub fn simulate(
     runner: &mut SimulationRunner,
     gap: usize,
     distribution: Option<Vec<f32>>,
-    mut out_data: Option<&mut Vec<OutData>>,
+    mut out_data: Option<&mut dyn OutDataSink>,
 ) {
-    let distribution =
-        distribution.unwrap_or_else(|| std::iter::repeat(1.0f32).take(gap).collect());
+    let distribution = distribution.unwrap_or_else(|| vec![1.0f32; gap]);
 
     let layers: Vec<usize> = (0..gap).collect();
 
@@ -13,16 +13,17 @@ use rayon::prelude::*;
 // internal
 use crate::network_behaviour::NetworkBehaviour;
 use crate::node::{
-    ComputeNode, MasterOmniscientNode, NetworkState, NoTx, Node, NodeId, Opinion, Vote,
+    ComputeNode, MasterOmniscientNode, NetworkStakes, NetworkState, NoTx, Node, NodeId, Opinion,
+    Vote,
 };
-use crate::output_processors::OutData;
+use crate::output_processors::{OutData, OutDataSink};
 use crate::settings::{
     ByzantineDistribution, ByzantineSettings, ConsensusSettings, SimulationSettings,
     SimulationStyle,
 };
 use crate::warding::{SimulationState, SimulationWard};
 use claro::{ClaroSolver, NodeQuery};
-use snowball::SnowballSolver;
+use snowball::{SlushSolver, SnowballSolver, SnowflakeSolver};
 
 /// Encapsulation solution for the simulations runner
 /// Holds the network state, the simulating nodes and the simulation settings.
@@ -100,7 +101,7 @@ impl SimulationRunner {
             .flat_map(|opinion| {
                 let size: usize =
                     (honest_size as f32 * distribution.weight_by_opinion(&opinion)) as usize;
-                std::iter::repeat(opinion).take(size)
+                (0..size).map(move |_| opinion)
             })
             .chain(std::iter::repeat(Opinion::None(NoTx)))
             .take(honest_size)
@@ -112,6 +113,8 @@ impl SimulationRunner {
         // shuffle distribution
         votes_distribution.shuffle(seed);
 
+        let stakes: NetworkStakes = settings.stake_distribution.stakes(*total_size, seed).into();
+
         // uninitialized network state, should be recalculated afterwards
         let network_state: NetworkState = Arc::new(RwLock::new(vec![None; *total_size]));
 
@@ -129,6 +132,7 @@ impl SimulationRunner {
             hones_nodes_ids.iter().copied().zip(votes_distribution),
             *total_size,
             Arc::clone(&network_state),
+            stakes,
             *consensus_settings,
             seed,
         )
@@ -181,8 +185,8 @@ impl SimulationRunner {
         let mut nodes: Vec<Node> = honest_nodes
             .into_iter()
             .chain(omniscient_nodes)
-            .chain(infantile_nodes.into_iter())
-            .chain(random_nodes.into_iter())
+            .chain(infantile_nodes)
+            .chain(random_nodes)
             .collect();
 
         nodes.sort_unstable_by_key(|node| node.inner_node().id());
@@ -197,20 +201,59 @@ impl SimulationRunner {
         node_data: impl Iterator<Item = (NodeId, Opinion)> + 'a,
         total_size: usize,
         network_state: NetworkState,
+        stakes: NetworkStakes,
         consensus_settings: ConsensusSettings,
         mut seed: &'a mut SmallRng,
     ) -> impl Iterator<Item = Node> + 'a {
         match consensus_settings {
+            ConsensusSettings::Slush(slush_settings) => {
+                node_data.map(Box::new(move |(node_id, opinion)| {
+                    Node::new_slush(
+                        node_id,
+                        SlushSolver::with_initial_opinion(
+                            slush_settings,
+                            NodeQuery::new(slush_settings.sample_size, node_id.to_string()),
+                            opinion,
+                        )
+                        .expect("Slush settings are checked before running"),
+                        Arc::clone(&network_state),
+                        Arc::clone(&stakes),
+                        SmallRng::from_rng(&mut seed)
+                            .expect("Rng should build properly from seed rng"),
+                    )
+                })
+                    as Box<dyn FnMut((usize, Opinion)) -> Node>)
+            }
+            ConsensusSettings::SnowFlake(snowflake_settings) => {
+                node_data.map(Box::new(move |(node_id, opinion)| {
+                    Node::new_snowflake(
+                        node_id,
+                        SnowflakeSolver::with_initial_opinion(
+                            snowflake_settings,
+                            NodeQuery::new(snowflake_settings.sample_size, node_id.to_string()),
+                            opinion,
+                        )
+                        .expect("Snowflake settings are checked before running"),
+                        Arc::clone(&network_state),
+                        Arc::clone(&stakes),
+                        SmallRng::from_rng(&mut seed)
+                            .expect("Rng should build properly from seed rng"),
+                    )
+                })
+                    as Box<dyn FnMut((usize, Opinion)) -> Node>)
+            }
             ConsensusSettings::SnowBall(snowball_settings) => {
                 node_data.map(Box::new(move |(node_id, opinion)| {
                     Node::new_snowball(
                         node_id,
                         SnowballSolver::with_initial_opinion(
                             snowball_settings,
-                            NodeQuery::new(total_size, node_id.to_string()),
+                            NodeQuery::new(snowball_settings.sample_size, node_id.to_string()),
                             opinion,
-                        ),
+                        )
+                        .expect("Snowball settings are checked before running"),
                         Arc::clone(&network_state),
+                        Arc::clone(&stakes),
                         SmallRng::from_rng(&mut seed)
                             .expect("Rng should build properly from seed rng"),
                     )
@@ -225,8 +268,24 @@ impl SimulationRunner {
                             claro_settings,
                             NodeQuery::new(total_size, node_id.to_string()),
                             opinion,
-                        ),
+                        )
+                        .expect("Claro settings are checked before running"),
+                        Arc::clone(&network_state),
+                        Arc::clone(&stakes),
+                        SmallRng::from_rng(&mut seed)
+                            .expect("Rng should build properly from seed rng"),
+                    )
+                })
+                    as Box<dyn FnMut((usize, Opinion)) -> Node>)
+            }
+            ConsensusSettings::Snowman(snowman_settings) => {
+                node_data.map(Box::new(move |(node_id, opinion)| {
+                    Node::new_snowman(
+                        node_id,
+                        snowman_settings,
+                        opinion,
                         Arc::clone(&network_state),
+                        Arc::clone(&stakes),
                         SmallRng::from_rng(&mut seed)
                             .expect("Rng should build properly from seed rng"),
                     )
@@ -242,7 +301,7 @@ impl SimulationRunner {
         nodes.par_iter().map(|node| node.vote()).collect()
     }
 
-    pub fn simulate(&mut self, out_data: Option<&mut Vec<OutData>>) {
+    pub fn simulate(&mut self, out_data: Option<&mut dyn OutDataSink>) {
         match self.settings.simulation_style.clone() {
             SimulationStyle::Sync => {
                 sync_runner::simulate(self, out_data);
@@ -268,13 +327,13 @@ impl SimulationRunner {
     fn dump_state_to_out_data(
         &self,
         simulation_state: &SimulationState,
-        out_ata: &mut Option<&mut Vec<OutData>>,
+        out_data: &mut Option<&mut dyn OutDataSink>,
     ) {
-        if let Some(out) = out_ata.as_deref_mut() {
+        if let Some(out) = out_data.as_deref_mut() {
             let nodes = self.nodes.read().unwrap();
             let iteration = simulation_state.iteration as u64;
             let round = simulation_state.round as u64;
-            let updated = nodes.iter().map(|node| {
+            let records = nodes.iter().map(|node| {
                 let node_type = node.type_as_string();
                 let vote = match node.vote() {
                     None => 0u8,
@@ -287,11 +346,11 @@ impl SimulationRunner {
                     _type: node_type,
                     round,
                     vote,
-                    state: node.serialized_state().get_serialized_state_record(),
+                    state: node.serialized_state().get_state_record(),
                 }
             });
 
-            out.extend(updated);
+            out.push_step(records.collect());
         }
     }
 
@@ -361,7 +420,8 @@ mod test {
         ByzantineDistribution, ByzantineSettings, ConsensusSettings, InitialDistribution,
         SimulationSettings,
     };
-    use claro::{ClaroConfiguration, QueryConfiguration};
+    use claro::{BootstrapPolicy, RoundFormula};
+    use claro::{ClaroConfiguration, EvidenceWeighting, QueryConfiguration, QueryGrowthPolicy};
     use rand::rngs::SmallRng;
     use rand::{thread_rng, SeedableRng};
 
@@ -374,11 +434,15 @@ mod test {
                 evidence_alpha_2: 0.0,
                 confidence_beta: 0.0,
                 look_ahead: 0,
+                weighting: EvidenceWeighting::Count,
+                formula: RoundFormula::Linear,
+                bootstrap: BootstrapPolicy::FirstVote,
                 query: QueryConfiguration {
                     query_size: 0,
                     initial_query_size: 0,
-                    query_multiplier: 0,
-                    max_multiplier: 0,
+                    query_multiplier: 1,
+                    max_multiplier: 1,
+                    growth_policy: QueryGrowthPolicy::Keep,
                 },
             }),
             distribution: InitialDistribution {
@@ -397,6 +461,7 @@ mod test {
             },
             wards: vec![],
             network_modifiers: vec![],
+            stake_distribution: Default::default(),
             seed: None,
         };
         let mut rng = SmallRng::from_rng(&mut thread_rng()).unwrap();
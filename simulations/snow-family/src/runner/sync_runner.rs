@@ -1,10 +1,10 @@
 use super::SimulationRunner;
-use crate::output_processors::OutData;
+use crate::output_processors::OutDataSink;
 use crate::warding::SimulationState;
 use std::sync::Arc;
 
 /// Simulate with option of dumping the network state as a `::polars::Series`
-pub fn simulate(runner: &mut SimulationRunner, mut out_data: Option<&mut Vec<OutData>>) {
+pub fn simulate(runner: &mut SimulationRunner, mut out_data: Option<&mut dyn OutDataSink>) {
     let mut state = SimulationState {
         network_state: Arc::clone(&runner.network_state),
         nodes: Arc::clone(&runner.nodes),
@@ -5,54 +5,56 @@ use std::fmt::Debug;
 use crate::network_behaviour::NetworkModifiers;
 use crate::node::Opinion;
 use crate::warding::Ward;
+use claro::NodeWeight;
+use rand::Rng;
 use serde::Deserialize;
 // internal
 
-/// Foreign Serialize, Deserialize implementation for `::snowball::SnowballConfiguration`
-#[derive(Debug, Deserialize)]
-#[serde(remote = "::snowball::SnowballConfiguration")]
-pub struct SnowballConfigurationDeSer {
-    pub quorum_size: usize,
-    pub sample_size: usize,
-    pub decision_threshold: usize,
-}
-
-/// Foreign Serialize, Deserialize implementation for `::claro::QueryConfiguration`
-#[derive(Debug, Deserialize)]
-#[serde(remote = "::claro::QueryConfiguration")]
-pub struct QueryConfigurationDeSer {
-    pub query_size: usize,
-    pub initial_query_size: usize,
-    pub query_multiplier: usize,
-    pub max_multiplier: usize,
-}
-
-/// Foreign Serialize, Deserialize implementation for `::claro::ClaroConfiguration`
-#[derive(Debug, Deserialize)]
-#[serde(remote = "::claro::ClaroConfiguration")]
-pub struct ClaroConfigurationDeSer {
-    pub evidence_alpha: f32,
-    pub evidence_alpha_2: f32,
-    pub confidence_beta: f32,
-    pub look_ahead: usize,
-    #[serde(with = "QueryConfigurationDeSer")]
-    pub query: ::claro::QueryConfiguration,
+/// Snowman chain settings
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct SnowmanSettings {
+    /// Blocks on each of the competing forks
+    pub chain_length: u64,
+    /// Algorithm deciding between competing blocks
+    pub solver: ::snowman::SnowmanConfiguration,
 }
 
 /// Consensus selector
 #[derive(Debug, Copy, Clone, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ConsensusSettings {
-    SnowBall(#[serde(with = "SnowballConfigurationDeSer")] ::snowball::SnowballConfiguration),
-    Claro(#[serde(with = "ClaroConfigurationDeSer")] ::claro::ClaroConfiguration),
+    Slush(::snowball::SlushConfiguration),
+    SnowFlake(::snowball::SnowflakeConfiguration),
+    SnowBall(::snowball::SnowballConfiguration),
+    Claro(::claro::ClaroConfiguration),
+    Snowman(SnowmanSettings),
 }
 
 impl ConsensusSettings {
     pub fn query_size(&self) -> usize {
         match self {
+            ConsensusSettings::Slush(slush) => slush.sample_size,
+            ConsensusSettings::SnowFlake(snowflake) => snowflake.sample_size,
             ConsensusSettings::SnowBall(snowball) => snowball.sample_size,
             ConsensusSettings::Claro(claro) => claro.query.query_size,
+            ConsensusSettings::Snowman(snowman) => snowman.solver.query_size(),
+        }
+    }
+
+    pub fn check_settings(&self) -> Result<(), Box<dyn Error>> {
+        match self {
+            ConsensusSettings::Slush(slush) => slush.validate()?,
+            ConsensusSettings::SnowFlake(snowflake) => snowflake.validate()?,
+            ConsensusSettings::SnowBall(snowball) => snowball.validate()?,
+            ConsensusSettings::Claro(claro) => claro.validate()?,
+            ConsensusSettings::Snowman(snowman) => {
+                if snowman.chain_length == 0 {
+                    return Err("snowman chain_length must be at least 1".into());
+                }
+                snowman.solver.validate()?
+            }
         }
+        Ok(())
     }
 }
 
@@ -95,6 +97,39 @@ impl ByzantineDistribution {
     }
 }
 
+/// Stake held by each node, used by stake weighted solvers
+#[derive(Debug, Copy, Clone, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StakeDistribution {
+    /// Every node holds the same stake of `1.0`
+    #[default]
+    Uniform,
+    /// Stakes drawn from a Pareto distribution with scale `1.0`
+    Pareto { shape: f64 },
+}
+
+impl StakeDistribution {
+    /// Draw a stake for each of `size` nodes
+    pub fn stakes(&self, size: usize, rng: &mut impl Rng) -> Vec<NodeWeight> {
+        match *self {
+            StakeDistribution::Uniform => vec![1.0; size],
+            StakeDistribution::Pareto { shape } => (0..size)
+                // inverse transform sampling, `1 - u` keeps clear of zero
+                .map(|_| (1.0 - rng.gen::<f64>()).powf(-1.0 / shape))
+                .collect(),
+        }
+    }
+
+    pub fn check_distribution(&self) -> Result<(), Box<dyn Error>> {
+        match self {
+            StakeDistribution::Pareto { shape } if *shape <= 0.0 => {
+                Err(format!("pareto stake shape must be positive, got {shape}").into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 /// Byzantine settings, size of simulation and byzantine distribution
 #[derive(Debug, Deserialize)]
 pub struct ByzantineSettings {
@@ -139,9 +174,21 @@ pub struct SimulationSettings {
     #[serde(default)]
     pub simulation_style: SimulationStyle,
     #[serde(default)]
+    pub stake_distribution: StakeDistribution,
+    #[serde(default)]
     pub seed: Option<u64>,
 }
 
+impl SimulationSettings {
+    /// Check every settings section is sound
+    pub fn check(&self) -> Result<(), Box<dyn Error>> {
+        self.consensus_settings.check_settings()?;
+        self.distribution.check_distribution()?;
+        self.byzantine_settings.distribution.check_distribution()?;
+        self.stake_distribution.check_distribution()
+    }
+}
+
 /// Check if a settings distribution is normalized (sum up to `1.0`)  
 fn check_normalized_distribution<T: Debug>(
     holder: T,
@@ -157,3 +204,25 @@ fn check_normalized_distribution<T: Debug>(
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::settings::StakeDistribution;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn stake_distributions() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        assert_eq!(StakeDistribution::Uniform.stakes(3, &mut rng), [1.0; 3]);
+
+        let pareto = StakeDistribution::Pareto { shape: 1.5 };
+        let stakes = pareto.stakes(1000, &mut rng);
+        assert_eq!(stakes.len(), 1000);
+        assert!(stakes.iter().all(|&stake| stake >= 1.0));
+        assert!(stakes.iter().any(|&stake| stake > 2.0));
+        assert!(StakeDistribution::Pareto { shape: 0.0 }
+            .check_distribution()
+            .is_err());
+    }
+}
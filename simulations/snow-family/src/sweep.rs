@@ -0,0 +1,299 @@
+// std
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+// crates
+use rayon::prelude::*;
+use serde::Deserialize;
+use serde_json::{Number, Value};
+// internal
+use crate::output_processors::{OutDataWriter, OutStateSchema, OutTag, OutputFormat};
+use crate::runner::SimulationRunner;
+use crate::settings::SimulationSettings;
+
+/// Values taken by a swept field
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum SweepValues {
+    /// Every value from `start` to `end`, both included, `step` apart
+    /// Integer bounds and step make an integer range.
+    Range {
+        start: Number,
+        end: Number,
+        step: Number,
+    },
+    /// Explicit values, any json value the field takes
+    List(Vec<Value>),
+}
+
+impl SweepValues {
+    pub fn values(&self) -> Result<Vec<Value>, Box<dyn Error>> {
+        match self {
+            SweepValues::List(values) if values.is_empty() => {
+                Err("sweep values list must not be empty".into())
+            }
+            SweepValues::List(values) => Ok(values.clone()),
+            SweepValues::Range { start, end, step } => {
+                if let (Some(start), Some(end), Some(step)) =
+                    (start.as_i64(), end.as_i64(), step.as_i64())
+                {
+                    if step <= 0 || end < start {
+                        return Err(invalid_range(start, end, step));
+                    }
+                    return Ok((start..=end)
+                        .step_by(step as usize)
+                        .map(Value::from)
+                        .collect());
+                }
+                let [start, end, step] = [start, end, step].map(|n| n.as_f64().unwrap_or(f64::NAN));
+                if !(step > 0.0 && end >= start) {
+                    return Err(invalid_range(start, end, step));
+                }
+                // tolerance so float steps do not miss the end of the range
+                let steps = ((end - start) / step + 1e-9).floor() as usize;
+                Ok((0..=steps)
+                    .map(|i| {
+                        // round away float noise such as 0.30000000000000004
+                        let value = ((start + i as f64 * step) * 1e9).round() / 1e9;
+                        Value::from(value)
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+fn invalid_range<T: Display>(start: T, end: T, step: T) -> Box<dyn Error> {
+    format!("invalid sweep range {start}..={end} by {step}").into()
+}
+
+/// Parameter sweep description
+/// Every combination of the swept values, their cartesian product, runs as its own simulation.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SweepSettings {
+    /// Settings every run starts from, on `SimulationSettings` format
+    pub base: Value,
+    /// Swept fields, keyed by their dot separated path within `base`, array items by index
+    /// (`wards.0.time_to_finality.ttf_threshold`). Every swept field must be present in `base`.
+    pub parameters: BTreeMap<String, SweepValues>,
+}
+
+/// Single sweep combination
+pub struct SweepRun {
+    /// Swept field paths and the values they take in this run
+    pub parameters: Vec<OutTag>,
+    pub settings: SimulationSettings,
+}
+
+impl SweepSettings {
+    /// Expand and check every combination, in lexicographic order of the parameter paths
+    pub fn runs(&self) -> Result<Vec<SweepRun>, Box<dyn Error>> {
+        let mut combinations: Vec<Vec<OutTag>> = vec![Vec::new()];
+        for (path, values) in &self.parameters {
+            let values = values.values()?;
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push((path.clone(), value.clone()));
+                        combination
+                    })
+                })
+                .collect();
+        }
+        combinations
+            .into_iter()
+            .map(|parameters| {
+                let mut settings = self.base.clone();
+                for (path, value) in &parameters {
+                    set_path(&mut settings, path, value.clone())?;
+                }
+                let settings: SimulationSettings = serde_json::from_value(settings)?;
+                settings.check()?;
+                Ok(SweepRun {
+                    parameters,
+                    settings,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Replace the value at the dot separated `path` within `settings`
+fn set_path(settings: &mut Value, path: &str, value: Value) -> Result<(), Box<dyn Error>> {
+    let mut current = settings;
+    for segment in path.split('.') {
+        current = match current {
+            Value::Object(fields) => fields.get_mut(segment),
+            Value::Array(items) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(move |index| items.get_mut(index)),
+            _ => None,
+        }
+        .ok_or_else(|| format!("sweep path {path} not found in base settings"))?;
+    }
+    *current = value;
+    Ok(())
+}
+
+/// Path of the output of the sweep run `index`, extension left for the writer to set
+/// The index goes right before the extension, so that it survives the writer replacing it even
+/// with dots in the file stem.
+fn run_out_path(out_path: &Path, index: usize) -> PathBuf {
+    let stem = out_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match out_path.extension() {
+        Some(extension) => format!("{stem}-{index:04}.{}", extension.to_string_lossy()),
+        None => format!("{stem}-{index:04}"),
+    };
+    out_path.with_file_name(file_name)
+}
+
+/// Run every sweep combination in parallel
+/// Run `i` is written to `out-000i.<format>` next to `out_path`, each record tagged with the run
+/// parameters.
+pub fn simulate_sweep(
+    runs: Vec<SweepRun>,
+    output_format: OutputFormat,
+    out_path: &Path,
+    batch_size: usize,
+) -> Result<(), Box<dyn Error>> {
+    runs.into_par_iter()
+        .enumerate()
+        .map(|(index, run)| {
+            let SweepRun {
+                parameters,
+                settings,
+            } = run;
+            let simulate = || {
+                let out_path = run_out_path(out_path, index);
+                let schema = OutStateSchema::from(&settings.consensus_settings);
+                let writer = OutDataWriter::new(output_format, &out_path, batch_size, schema)?;
+                let mut writer = parameters
+                    .into_iter()
+                    .fold(writer, |writer, (name, value)| writer.with_tag(name, value));
+                SimulationRunner::new(settings).simulate(Some(&mut writer));
+                writer.finish()
+            };
+            // errors are not `Send`, keep their message only to get them out of the thread pool
+            simulate().map_err(|e| format!("sweep run {index}: {e}"))
+        })
+        .collect::<Result<(), String>>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sweep::{run_out_path, set_path, SweepSettings, SweepValues};
+    use serde_json::{json, Value};
+    use std::path::Path;
+
+    fn sweep(parameters: Value) -> SweepSettings {
+        serde_json::from_value(json!({
+            "base": {
+                "consensus_settings": {
+                    "snow_ball": {
+                        "quorum_size": 14,
+                        "sample_size": 20,
+                        "decision_threshold": 20
+                    }
+                },
+                "distribution": { "yes": 0.6, "no": 0.4, "none": 0.0 },
+                "byzantine_settings": {
+                    "total_size": 100,
+                    "distribution": {
+                        "honest": 1.0,
+                        "infantile": 0.0,
+                        "random": 0.0,
+                        "omniscient": 0.0
+                    }
+                },
+                "wards": [{ "time_to_finality": { "ttf_threshold": 1 } }],
+                "seed": 18042022
+            },
+            "parameters": parameters
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn ranges() {
+        let integers: SweepValues =
+            serde_json::from_value(json!({ "start": 10, "end": 20, "step": 5 })).unwrap();
+        assert_eq!(
+            integers.values().unwrap(),
+            vec![json!(10), json!(15), json!(20)]
+        );
+        let floats: SweepValues =
+            serde_json::from_value(json!({ "start": 0.1, "end": 0.3, "step": 0.1 })).unwrap();
+        assert_eq!(
+            floats.values().unwrap(),
+            vec![json!(0.1), json!(0.2), json!(0.3)]
+        );
+        let empty: SweepValues =
+            serde_json::from_value(json!({ "start": 2, "end": 1, "step": 1 })).unwrap();
+        assert!(empty.values().is_err());
+    }
+
+    #[test]
+    fn cartesian_product() {
+        let sweep = sweep(json!({
+            "consensus_settings.snow_ball.quorum_size": { "start": 12, "end": 16, "step": 2 },
+            "byzantine_settings.distribution": [
+                { "honest": 1.0, "infantile": 0.0, "random": 0.0, "omniscient": 0.0 },
+                { "honest": 0.9, "infantile": 0.0, "random": 0.1, "omniscient": 0.0 }
+            ],
+            "wards.0.time_to_finality.ttf_threshold": [1]
+        }));
+        let runs = sweep.runs().unwrap();
+        assert_eq!(runs.len(), 6);
+        let names: Vec<&str> = runs[0]
+            .parameters
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "byzantine_settings.distribution",
+                "consensus_settings.snow_ball.quorum_size",
+                "wards.0.time_to_finality.ttf_threshold"
+            ]
+        );
+        assert_eq!(runs[1].parameters[1].1, json!(14));
+        assert_eq!(runs[5].settings.byzantine_settings.distribution.random, 0.1);
+        assert_eq!(runs[5].settings.consensus_settings.query_size(), 20);
+    }
+
+    #[test]
+    fn unknown_or_invalid_parameters() {
+        let typo = sweep(json!({ "consensus_settings.snow_ball.quorum": [12] }));
+        assert!(typo.runs().is_err());
+        let unnormalized = sweep(json!({ "distribution.yes": [0.9] }));
+        assert!(unnormalized.runs().is_err());
+        let mut value = json!({ "wards": [] });
+        assert!(set_path(&mut value, "wards.0", json!(1)).is_err());
+    }
+
+    #[test]
+    fn run_out_paths_keep_dotted_stems_apart() {
+        let out_path = Path::new("out/results.v2.parquet");
+        let first = run_out_path(out_path, 1).with_extension("parquet");
+        let second = run_out_path(out_path, 2).with_extension("parquet");
+        assert_eq!(first, Path::new("out/results.v2-0001.parquet"));
+        assert_eq!(second, Path::new("out/results.v2-0002.parquet"));
+        assert_eq!(
+            run_out_path(out_path, 1).with_extension("00000.parquet"),
+            Path::new("out/results.v2-0001.00000.parquet")
+        );
+        assert_eq!(
+            run_out_path(Path::new("results"), 3).with_extension("csv"),
+            Path::new("results-0003.csv")
+        );
+    }
+}
@@ -10,6 +10,11 @@ pub struct ConvergedWard {
 }
 
 impl ConvergedWard {
+    /// Ward triggering once a `ratio`, within `[0.0, 1.0]`, of the nodes is decided
+    pub fn new(ratio: f32) -> Self {
+        Self { ratio }
+    }
+
     pub fn converged(&self, len: usize, decisions: impl Iterator<Item = Decision>) -> bool {
         let total_decided = decisions
             .filter(|decision| matches!(decision, Decision::Decided(_)))
@@ -38,7 +43,7 @@ where
         .contains(&value)
         .then_some(value)
         .ok_or_else(|| {
-            D::Error::custom(&format!(
+            D::Error::custom(format!(
                 "Only normalized values [0.0, 1.0] are valid, got: {}",
                 value
             ))
@@ -2,9 +2,9 @@ use crate::node::{NetworkState, Node};
 use serde::Deserialize;
 use std::sync::{Arc, RwLock};
 
-mod converged;
-mod stabilised;
-mod ttf;
+pub mod converged;
+pub mod stabilised;
+pub mod ttf;
 
 pub struct SimulationState {
     pub network_state: NetworkState,
@@ -22,7 +22,7 @@ pub enum StabilisedCheck {
 impl StabilisedCheck {
     pub fn should_check(&mut self, state: &SimulationState) -> bool {
         match self {
-            StabilisedCheck::Iterations { chunk } => (state.iteration % *chunk) == 0,
+            StabilisedCheck::Iterations { chunk } => state.iteration.checked_rem(*chunk) == Some(0),
             StabilisedCheck::Rounds { last_round } => {
                 let different_round = *last_round < state.round;
                 *last_round = state.round;
@@ -40,6 +40,14 @@ pub struct StabilisedWard {
 }
 
 impl StabilisedWard {
+    /// Ward triggering once the last `buffer_size` checked network states hold the same votes
+    pub fn new(buffer_size: usize, check: StabilisedCheck) -> Self {
+        Self {
+            buffer: FixedSliceDeque::new(buffer_size),
+            check,
+        }
+    }
+
     fn is_stabilised(&self) -> bool {
         if self.buffer.is_full() {
             let set: HashSet<_> = self.buffer.iter().copied().collect();
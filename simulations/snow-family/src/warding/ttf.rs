@@ -8,6 +8,12 @@ pub struct TimeToFinalityWard {
     ttf_threshold: usize,
 }
 
+impl TimeToFinalityWard {
+    pub fn new(ttf_threshold: usize) -> Self {
+        Self { ttf_threshold }
+    }
+}
+
 impl SimulationWard for TimeToFinalityWard {
     type SimulationState = SimulationState;
     fn analyze(&mut self, state: &SimulationState) -> bool {